@@ -0,0 +1,20 @@
+#![no_main]
+use std::io;
+
+use gps_util::port_buffer::{PortBuffer, MAX_BUFFER_SIZE};
+use libfuzzer_sys::fuzz_target;
+
+// The first byte decides how the rest of the input is chunked, so that frames split across
+// reads get exercised too.
+fuzz_target!(|data: &[u8]| {
+    let (chunk_size, data) = match data.split_first() {
+        Some((chunk_size, data)) => (*chunk_size as usize + 1, data),
+        None => return,
+    };
+    let mut port = PortBuffer::new(io::empty());
+    for chunk in data.chunks(chunk_size) {
+        port.feed(chunk);
+        while port.read_msg().is_some() {}
+        assert!(port.buffered_bytes() <= MAX_BUFFER_SIZE);
+    }
+});
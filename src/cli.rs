@@ -0,0 +1,227 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+use gps_util::{
+    config::ReceiverConfig,
+    profile::{self, ProfileError},
+    ublox::{
+        parse_msg_class_id, CfgLayer, GnssId, UbxCfgRateTimeRef, UbxCfgRstMode, UbxCfgRstStart,
+    },
+};
+
+#[derive(Debug, Parser)]
+#[clap(name = "gps-util", version, about = "Tools for u-blox GPS receivers")]
+pub struct Opts {
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Show the satellites tracked by the receiver in a 3D view
+    #[cfg(feature = "visualizer")]
+    View {
+        #[clap(flatten)]
+        port: PortOpts,
+        #[clap(flatten)]
+        config: ConfigOpts,
+        /// Use the receiver as it is, without configuring it first
+        #[clap(long, action)]
+        no_configure: bool,
+        #[clap(flatten)]
+        record: RecordOpts,
+    },
+    /// Record the raw stream from the receiver to a .ubx log
+    Record {
+        /// The log file to write
+        #[clap(value_parser)]
+        file: PathBuf,
+        /// Also write an index of host receive times to FILE.idx
+        #[clap(long, action)]
+        index: bool,
+        #[clap(flatten)]
+        port: PortOpts,
+        #[clap(flatten)]
+        config: ConfigOpts,
+        /// Use the receiver as it is, without configuring it first
+        #[clap(long, action)]
+        no_configure: bool,
+    },
+    /// Play back a recorded log, in the 3D view if available
+    Replay {
+        /// The log to play back
+        #[clap(value_parser)]
+        file: PathBuf,
+        /// Playback speed relative to real time
+        #[clap(long, default_value = "1", value_parser)]
+        speed: f64,
+        /// Play the log as fast as possible instead of in real time
+        #[clap(long, action)]
+        fast: bool,
+        /// Decode frames with bad checksums instead of skipping them
+        #[clap(long, action)]
+        lenient: bool,
+        /// Print the messages instead of showing the 3D view
+        #[cfg(feature = "visualizer")]
+        #[clap(long, action)]
+        no_view: bool,
+    },
+    /// Decode and print the messages coming from the receiver or stored in a log file
+    Decode {
+        /// A recorded log to decode instead of reading from the receiver
+        #[clap(value_parser)]
+        file: Option<PathBuf>,
+        #[clap(flatten)]
+        port: PortOpts,
+        /// Decode frames with bad checksums instead of skipping them
+        #[clap(long, action)]
+        lenient: bool,
+    },
+    /// Compute positions from the raw measurements in a log and compare them with the receiver's
+    Solve {
+        /// The log to process
+        #[clap(value_parser)]
+        file: PathBuf,
+        /// Leave out satellites below this elevation, in degrees
+        #[clap(long, default_value = "10", value_parser)]
+        elevation_mask: f64,
+        /// Decode frames with bad checksums instead of skipping them
+        #[clap(long, action)]
+        lenient: bool,
+    },
+    /// Forecast the DOP at a site from the almanac and ephemerides collected in a log
+    Forecast {
+        /// A log with the almanac of the whole constellation
+        #[clap(value_parser)]
+        file: PathBuf,
+        /// Latitude of the site in degrees, instead of the receiver position in the log
+        #[clap(long, requires = "lon", allow_hyphen_values = true, value_parser)]
+        lat: Option<f64>,
+        /// Longitude of the site in degrees
+        #[clap(long, requires = "lat", allow_hyphen_values = true, value_parser)]
+        lon: Option<f64>,
+        /// Height of the site above the ellipsoid in meters
+        #[clap(long, default_value = "0", allow_hyphen_values = true, value_parser)]
+        height: f64,
+        /// How far ahead to forecast, in hours
+        #[clap(long, default_value = "24", value_parser)]
+        hours: f64,
+        /// Time between forecast points, in minutes
+        #[clap(long, default_value = "10", value_parser)]
+        step: f64,
+        /// Leave out satellites below this elevation, in degrees
+        #[clap(long, default_value = "10", value_parser)]
+        elevation_mask: f64,
+        /// Only list the periods when the PDOP is at most this
+        #[clap(long, value_parser)]
+        max_pdop: Option<f64>,
+        /// Decode frames with bad checksums instead of skipping them
+        #[clap(long, action)]
+        lenient: bool,
+    },
+    /// Configure the receiver and exit
+    Configure {
+        #[clap(flatten)]
+        port: PortOpts,
+        #[clap(flatten)]
+        config: ConfigOpts,
+        /// Only show how the receiver's settings differ from the configuration
+        #[clap(long, action)]
+        dry_run: bool,
+    },
+    /// Save the receiver's current configuration to its permanent storage
+    Save {
+        #[clap(flatten)]
+        port: PortOpts,
+    },
+    /// Restore the receiver's default configuration, including the permanent one
+    Revert {
+        #[clap(flatten)]
+        port: PortOpts,
+    },
+    /// Reset the receiver
+    Reset {
+        #[clap(flatten)]
+        port: PortOpts,
+        /// Navigation data to keep: hot, warm or cold
+        #[clap(long, default_value = "cold", value_parser)]
+        start: UbxCfgRstStart,
+        /// What to reset: hardware, software or gnss
+        #[clap(long, default_value = "gnss", value_parser)]
+        mode: UbxCfgRstMode,
+    },
+    /// Print the configuration of a generation 9 receiver
+    DumpConfig {
+        #[clap(flatten)]
+        port: PortOpts,
+        /// Configuration layer to read: ram, bbr, flash or default
+        #[clap(long, default_value = "ram", value_parser)]
+        layer: CfgLayer,
+    },
+    /// Show the receiver's hardware and firmware versions and capabilities
+    Info {
+        #[clap(flatten)]
+        port: PortOpts,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct PortOpts {
+    /// Serial device the receiver is connected to
+    #[clap(short, long, default_value = "/dev/ttyACM0", value_parser)]
+    pub device: String,
+    /// Baud rate of the serial device
+    #[clap(short, long, default_value = "9600", value_parser)]
+    pub baud: u32,
+}
+
+#[cfg(feature = "visualizer")]
+#[derive(Debug, Args)]
+pub struct RecordOpts {
+    /// Record the raw stream from the receiver to a .ubx log
+    #[clap(long, value_parser)]
+    pub record: Option<PathBuf>,
+    /// Also write an index of host receive times next to the log
+    #[clap(long, requires = "record", action)]
+    pub index: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigOpts {
+    /// Read the configuration from a TOML profile instead of the options below
+    #[clap(long, value_parser)]
+    pub profile: Option<PathBuf>,
+    /// Constellations to track: gps, galileo, glonass, beidou, qzss, sbas, imes
+    #[clap(long, default_value = "gps", use_value_delimiter = true, value_parser)]
+    pub gnss: Vec<GnssId>,
+    /// Measurement period in milliseconds
+    #[clap(long, default_value = "1000", value_parser)]
+    pub meas_rate: u16,
+    /// Number of measurements per navigation solution
+    #[clap(long, default_value = "1", value_parser)]
+    pub nav_rate: u16,
+    /// UBX messages to enable, by name (like RXM-RAWX) or as CLASS:ID in hex (like 02:15)
+    #[clap(
+        long = "msg",
+        default_value = "RXM-SFRBX,RXM-RAWX,NAV-PVT,NAV-SAT",
+        use_value_delimiter = true,
+        value_parser = parse_msg_class_id
+    )]
+    pub messages: Vec<(u8, u8)>,
+}
+
+impl ConfigOpts {
+    pub fn receiver_config(&self) -> Result<ReceiverConfig, ProfileError> {
+        if let Some(path) = &self.profile {
+            return profile::load(path);
+        }
+        Ok(ReceiverConfig {
+            gnss: self.gnss.clone(),
+            meas_rate_ms: self.meas_rate,
+            nav_rate_cycles: self.nav_rate,
+            time_ref: UbxCfgRateTimeRef::Gps,
+            messages: self.messages.clone(),
+            ..ReceiverConfig::default()
+        })
+    }
+}
@@ -0,0 +1,375 @@
+use std::{
+    fmt,
+    io::{Read, Write},
+    time::Duration,
+};
+
+use crate::{
+    port_buffer::{PortBuffer, RequestError},
+    ublox::{
+        msg_name, CfgItem, CfgKey, CfgLayer, CfgLayers, GnssId, UbloxMsg, UbxCfgGnss, UbxCfgMsg,
+        UbxCfgPrt, UbxCfgPrtInMask, UbxCfgPrtOutMask, UbxCfgPrtPort, UbxCfgPrtSettings, UbxCfgRate,
+        UbxCfgRateSettings, UbxCfgRateTimeRef, UbxCfgValGet, UbxCfgValSet, UbxNavPvt, UbxNavSat,
+        UbxRxmRawx, UbxRxmSfrbx, MAX_CFG_ITEMS,
+    },
+};
+
+const ALL_GNSS: [GnssId; 7] = [
+    GnssId::Gps,
+    GnssId::Sbas,
+    GnssId::Galileo,
+    GnssId::BeiDou,
+    GnssId::Imes,
+    GnssId::Qzss,
+    GnssId::Glonass,
+];
+
+/// The receiver setup the tool needs: which constellations to track, how often to measure and
+/// which UBX messages to output on the port the tool is connected to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceiverConfig {
+    pub port: UbxCfgPrtSettings,
+    pub gnss: Vec<GnssId>,
+    pub meas_rate_ms: u16,
+    pub nav_rate_cycles: u16,
+    pub time_ref: UbxCfgRateTimeRef,
+    /// Class and ID of the messages to output once per navigation solution.
+    pub messages: Vec<(u8, u8)>,
+}
+
+impl Default for ReceiverConfig {
+    fn default() -> Self {
+        ReceiverConfig {
+            port: UbxCfgPrtSettings::usb(UbxCfgPrtInMask::UBX, UbxCfgPrtOutMask::UBX),
+            gnss: vec![GnssId::Gps],
+            meas_rate_ms: 1000,
+            nav_rate_cycles: 1,
+            time_ref: UbxCfgRateTimeRef::Gps,
+            messages: vec![
+                (UbxRxmSfrbx::CLASS, UbxRxmSfrbx::ID),
+                (UbxRxmRawx::CLASS, UbxRxmRawx::ID),
+                (UbxNavPvt::CLASS, UbxNavPvt::ID),
+                (UbxNavSat::CLASS, UbxNavSat::ID),
+            ],
+        }
+    }
+}
+
+impl ReceiverConfig {
+    /// The messages to send right after opening the port. Constellations can only be set once
+    /// the current CFG-GNSS settings are known, so the sequence ends with a CFG-GNSS poll - pass
+    /// the response to `gnss_settings`.
+    pub fn startup_messages(&self) -> Vec<UbloxMsg> {
+        let mut result = vec![
+            UbloxMsg::CfgPrt(UbxCfgPrt::Set(self.port)),
+            UbloxMsg::CfgRate(UbxCfgRate::Set(UbxCfgRateSettings {
+                meas_rate_ms: self.meas_rate_ms,
+                nav_rate_cycles: self.nav_rate_cycles,
+                time_ref: self.time_ref,
+            })),
+        ];
+        // CFG-MSG with a single rate applies to the port the message is received on
+        for &(class, id) in &self.messages {
+            result.push(UbloxMsg::CfgMsg(UbxCfgMsg::SetRate { class, id, rate: 1 }));
+        }
+        result.push(UbloxMsg::CfgGnss(UbxCfgGnss::Poll));
+        result
+    }
+
+    /// Adjusts the receiver's CFG-GNSS settings so that only the configured constellations are
+    /// enabled. A single constellation gets all of its tracking channels reserved.
+    pub fn gnss_settings(&self, current: UbxCfgGnss) -> Option<UbxCfgGnss> {
+        match current {
+            UbxCfgGnss::Poll => None,
+            UbxCfgGnss::Settings {
+                version,
+                num_trk_ch_hw,
+                num_trk_ch_use,
+                config_blocks,
+            } => {
+                let config_blocks = config_blocks
+                    .into_iter()
+                    .map(|mut block| {
+                        block.enabled = self.gnss.contains(&block.gnss_id);
+                        if block.enabled && self.gnss.len() == 1 {
+                            block.res_trk_ch = block.max_trk_ch;
+                        }
+                        block
+                    })
+                    .collect();
+                Some(UbxCfgGnss::Settings {
+                    version,
+                    num_trk_ch_hw,
+                    num_trk_ch_use,
+                    config_blocks,
+                })
+            }
+        }
+    }
+
+    /// The same setup as items of the configuration database of generation 9 receivers. Fails
+    /// if one of the settings has no item in the key database.
+    pub fn config_items(&self) -> Result<Vec<CfgItem>, String> {
+        let port = port_key_name(&self.port.port);
+        let key = |name: String| {
+            CfgKey::from_name(&name).ok_or(format!("unknown configuration item: {}", name))
+        };
+        let in_mask = self.port.in_mask;
+        let out_mask = self.port.out_mask;
+        let mut result = vec![
+            CfgItem::bool(
+                key(format!("CFG-{}INPROT-UBX", port))?,
+                in_mask.contains(UbxCfgPrtInMask::UBX),
+            ),
+            CfgItem::bool(
+                key(format!("CFG-{}INPROT-NMEA", port))?,
+                in_mask.contains(UbxCfgPrtInMask::NMEA),
+            ),
+            CfgItem::bool(
+                key(format!("CFG-{}OUTPROT-UBX", port))?,
+                out_mask.contains(UbxCfgPrtOutMask::UBX),
+            ),
+            CfgItem::bool(
+                key(format!("CFG-{}OUTPROT-NMEA", port))?,
+                out_mask.contains(UbxCfgPrtOutMask::NMEA),
+            ),
+        ];
+        if let UbxCfgPrtPort::Uart { baud_rate, .. } = self.port.port {
+            let baud_rate_key = key(format!("CFG-{}-BAUDRATE", port))?;
+            result.push(CfgItem::new(baud_rate_key, baud_rate as u64));
+        }
+        result.extend_from_slice(&[
+            CfgItem::new(CfgKey::RATE_MEAS, self.meas_rate_ms as u64),
+            CfgItem::new(CfgKey::RATE_NAV, self.nav_rate_cycles as u64),
+            CfgItem::new(CfgKey::RATE_TIMEREF, self.time_ref as u64),
+        ]);
+        for &(class, id) in &self.messages {
+            let key = CfgKey::msgout(class, id, port).ok_or_else(|| {
+                format!(
+                    "no output rate item for message 0x{:02x} 0x{:02x}",
+                    class, id
+                )
+            })?;
+            result.push(CfgItem::new(key, 1));
+        }
+        for gnss in ALL_GNSS {
+            if let Some(key) = CfgKey::signal_enable(gnss) {
+                result.push(CfgItem::bool(key, self.gnss.contains(&gnss)));
+            }
+        }
+        Ok(result)
+    }
+
+    /// CFG-VALSET messages applying `config_items` to the given layers.
+    pub fn valset_messages(&self, layers: CfgLayers) -> Result<Vec<UbloxMsg>, String> {
+        Ok(UbxCfgValSet::messages(layers, self.config_items()?)
+            .into_iter()
+            .map(UbloxMsg::CfgValSet)
+            .collect())
+    }
+}
+
+/// The name of a port in the keys of the configuration database.
+fn port_key_name(port: &UbxCfgPrtPort) -> &'static str {
+    match *port {
+        UbxCfgPrtPort::I2c { .. } => "I2C",
+        UbxCfgPrtPort::Uart { port_id: 2, .. } => "UART2",
+        UbxCfgPrtPort::Uart { .. } => "UART1",
+        UbxCfgPrtPort::Usb => "USB",
+        UbxCfgPrtPort::Spi { .. } => "SPI",
+    }
+}
+
+/// A setting whose value in the receiver differs from the configuration. `current` is
+/// "unknown" if the receiver didn't report it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigDifference {
+    pub setting: String,
+    pub current: String,
+    pub wanted: String,
+}
+
+impl ConfigDifference {
+    fn check<T: PartialEq + fmt::Debug>(
+        result: &mut Vec<ConfigDifference>,
+        setting: &str,
+        current: Option<T>,
+        wanted: T,
+    ) {
+        if current.as_ref() != Some(&wanted) {
+            result.push(ConfigDifference {
+                setting: setting.to_owned(),
+                current: current
+                    .map_or_else(|| "unknown".to_owned(), |value| format!("{:?}", value)),
+                wanted: format!("{:?}", wanted),
+            });
+        }
+    }
+}
+
+impl fmt::Display for ConfigDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.setting, self.current, self.wanted)
+    }
+}
+
+/// Polls a setting, treating a NAK or a missing response as an unknown value.
+fn poll_setting<P: Read + Write>(
+    port: &mut PortBuffer<P>,
+    msg: UbloxMsg,
+    timeout: Duration,
+    retries: usize,
+) -> Result<Option<UbloxMsg>, RequestError> {
+    match port.poll(msg, timeout, retries) {
+        Ok(response) => Ok(Some(response)),
+        Err(RequestError::Io(err)) => Err(RequestError::Io(err)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Compares the settings of a receiver configured with the legacy CFG messages with `config`.
+pub fn compare_legacy_config<P: Read + Write>(
+    port: &mut PortBuffer<P>,
+    config: &ReceiverConfig,
+    timeout: Duration,
+    retries: usize,
+) -> Result<Vec<ConfigDifference>, RequestError> {
+    let mut result = vec![];
+
+    let port_id = config.port.port.port_id();
+    let poll = UbloxMsg::CfgPrt(UbxCfgPrt::Get { port_id });
+    let current = match poll_setting(port, poll, timeout, retries)? {
+        Some(UbloxMsg::CfgPrt(UbxCfgPrt::Set(settings))) => Some(settings),
+        _ => None,
+    };
+    let wanted = config.port;
+    ConfigDifference::check(&mut result, "port", current.map(|s| s.port), wanted.port);
+    let setting = "input protocols";
+    ConfigDifference::check(
+        &mut result,
+        setting,
+        current.map(|s| s.in_mask),
+        wanted.in_mask,
+    );
+    let setting = "output protocols";
+    ConfigDifference::check(
+        &mut result,
+        setting,
+        current.map(|s| s.out_mask),
+        wanted.out_mask,
+    );
+
+    let poll = UbloxMsg::CfgRate(UbxCfgRate::Poll);
+    let current = match poll_setting(port, poll, timeout, retries)? {
+        Some(UbloxMsg::CfgRate(UbxCfgRate::Set(rate))) => Some(rate),
+        _ => None,
+    };
+    let wanted = UbxCfgRateSettings {
+        meas_rate_ms: config.meas_rate_ms,
+        nav_rate_cycles: config.nav_rate_cycles,
+        time_ref: config.time_ref,
+    };
+    ConfigDifference::check(&mut result, "rate", current, wanted);
+
+    for &(class, id) in &config.messages {
+        let poll = UbloxMsg::CfgMsg(UbxCfgMsg::Get { class, id });
+        let current = match poll_setting(port, poll, timeout, retries)? {
+            Some(UbloxMsg::CfgMsg(UbxCfgMsg::SetRatePorts { rate, .. })) => {
+                rate.get(port_id as usize).copied()
+            }
+            _ => None,
+        };
+        let setting = match msg_name(class, id) {
+            Some(name) => format!("{} rate", name),
+            None => format!("0x{:02x} 0x{:02x} rate", class, id),
+        };
+        ConfigDifference::check(&mut result, &setting, current, 1);
+    }
+
+    let sorted = |mut gnss: Vec<GnssId>| {
+        gnss.sort_by_key(|gnss| *gnss as u8);
+        gnss
+    };
+    let poll = UbloxMsg::CfgGnss(UbxCfgGnss::Poll);
+    let current = match poll_setting(port, poll, timeout, retries)? {
+        Some(UbloxMsg::CfgGnss(UbxCfgGnss::Settings { config_blocks, .. })) => Some(sorted(
+            config_blocks
+                .iter()
+                .filter(|block| block.enabled)
+                .map(|block| block.gnss_id)
+                .collect(),
+        )),
+        _ => None,
+    };
+    let wanted = sorted(config.gnss.clone());
+    ConfigDifference::check(&mut result, "constellations", current, wanted);
+
+    Ok(result)
+}
+
+/// Compares the RAM layer of the configuration database of a generation 9 receiver with `items`.
+pub fn compare_config_items<P: Read + Write>(
+    port: &mut PortBuffer<P>,
+    items: &[CfgItem],
+    timeout: Duration,
+    retries: usize,
+) -> Result<Vec<ConfigDifference>, RequestError> {
+    let mut current = vec![];
+    for chunk in items.chunks(MAX_CFG_ITEMS) {
+        let request = UbxCfgValGet::Request {
+            layer: CfgLayer::Ram,
+            position: 0,
+            keys: chunk.iter().map(|item| item.key).collect(),
+        };
+        if let Some(UbloxMsg::CfgValGet(UbxCfgValGet::Response { items, .. })) =
+            poll_setting(port, UbloxMsg::CfgValGet(request), timeout, retries)?
+        {
+            current.extend(items);
+        }
+    }
+    let result = items
+        .iter()
+        .filter_map(|wanted| {
+            let current = current.iter().find(|item| item.key == wanted.key);
+            if current == Some(wanted) {
+                return None;
+            }
+            Some(ConfigDifference {
+                setting: wanted.key.to_string(),
+                current: current.map_or_else(|| "unknown".to_owned(), CfgItem::value_string),
+                wanted: wanted.value_string(),
+            })
+        })
+        .collect();
+    Ok(result)
+}
+
+/// Reads the whole configuration of a generation 9 receiver from the given layer.
+pub fn read_receiver_config<P: Read + Write>(
+    port: &mut PortBuffer<P>,
+    layer: CfgLayer,
+    timeout: Duration,
+    retries: usize,
+) -> Result<Vec<CfgItem>, RequestError> {
+    let mut result = vec![];
+    loop {
+        let request = UbxCfgValGet::Request {
+            layer,
+            position: result.len() as u16,
+            keys: vec![CfgKey::ALL],
+        };
+        match port.poll(UbloxMsg::CfgValGet(request), timeout, retries) {
+            Ok(UbloxMsg::CfgValGet(UbxCfgValGet::Response { items, .. })) => {
+                let num_items = items.len();
+                result.extend(items);
+                if num_items < MAX_CFG_ITEMS {
+                    return Ok(result);
+                }
+            }
+            Ok(_) => return Ok(result),
+            // asking for more items than there are is rejected
+            Err(RequestError::Nak { .. }) if !result.is_empty() => return Ok(result),
+            Err(err) => return Err(err),
+        }
+    }
+}
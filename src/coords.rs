@@ -0,0 +1,124 @@
+use nalgebra::Vector3;
+
+/// WGS84 semi-major axis, in meters.
+pub const WGS84_A: f64 = 6378137.0;
+/// WGS84 flattening.
+pub const WGS84_F: f64 = 1.0 / 298.257223563;
+/// WGS84 first eccentricity squared.
+pub const WGS84_E2: f64 = WGS84_F * (2.0 - WGS84_F);
+
+/// A position given by latitude and longitude in degrees and height above the WGS84 ellipsoid
+/// in meters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Geodetic {
+    pub lat: f64,
+    pub lon: f64,
+    pub height: f64,
+}
+
+impl Geodetic {
+    pub fn new(lat: f64, lon: f64, height: f64) -> Self {
+        Self { lat, lon, height }
+    }
+
+    pub fn to_ecef(&self) -> Vector3<f64> {
+        let (sin_lat, cos_lat) = self.lat.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = self.lon.to_radians().sin_cos();
+        let n = WGS84_A / (1.0 - WGS84_E2 * sin_lat * sin_lat).sqrt();
+        Vector3::new(
+            (n + self.height) * cos_lat * cos_lon,
+            (n + self.height) * cos_lat * sin_lon,
+            (n * (1.0 - WGS84_E2) + self.height) * sin_lat,
+        )
+    }
+
+    /// Converts an ECEF position to geodetic coordinates, iterating on the latitude.
+    pub fn from_ecef(ecef: Vector3<f64>) -> Self {
+        let p = (ecef.x * ecef.x + ecef.y * ecef.y).sqrt();
+        let lon = ecef.y.atan2(ecef.x);
+        let mut lat = ecef.z.atan2(p * (1.0 - WGS84_E2));
+        let mut height = 0.0;
+        for _ in 0..10 {
+            let sin_lat = lat.sin();
+            let n = WGS84_A / (1.0 - WGS84_E2 * sin_lat * sin_lat).sqrt();
+            height = if lat.cos().abs() > 1e-9 {
+                p / lat.cos() - n
+            } else {
+                ecef.z.abs() - n * (1.0 - WGS84_E2)
+            };
+            let new_lat = ecef.z.atan2(p * (1.0 - WGS84_E2 * n / (n + height)));
+            let converged = (new_lat - lat).abs() < 1e-12;
+            lat = new_lat;
+            if converged {
+                break;
+            }
+        }
+        Self {
+            lat: lat.to_degrees(),
+            lon: lon.to_degrees(),
+            height,
+        }
+    }
+
+    /// The east, north and up components of the vector from this position to an ECEF point.
+    pub fn to_enu(&self, target: Vector3<f64>) -> Vector3<f64> {
+        self.rotate_to_enu(target - self.to_ecef())
+    }
+
+    /// The north, east and down components of an ECEF vector, like a velocity, at this position.
+    pub fn ecef_to_ned(&self, vector: Vector3<f64>) -> Vector3<f64> {
+        let enu = self.rotate_to_enu(vector);
+        Vector3::new(enu.y, enu.x, -enu.z)
+    }
+
+    fn rotate_to_enu(&self, d: Vector3<f64>) -> Vector3<f64> {
+        let (sin_lat, cos_lat) = self.lat.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = self.lon.to_radians().sin_cos();
+        Vector3::new(
+            -sin_lon * d.x + cos_lon * d.y,
+            -sin_lat * cos_lon * d.x - sin_lat * sin_lon * d.y + cos_lat * d.z,
+            cos_lat * cos_lon * d.x + cos_lat * sin_lon * d.y + sin_lat * d.z,
+        )
+    }
+
+    /// Azimuth (0 to 360, clockwise from north) and elevation of an ECEF point as seen from this
+    /// position, in degrees.
+    pub fn look_angles(&self, target: Vector3<f64>) -> (f64, f64) {
+        let enu = self.to_enu(target);
+        let azimuth = enu.x.atan2(enu.y).to_degrees().rem_euclid(360.0);
+        let elevation = enu
+            .z
+            .atan2((enu.x * enu.x + enu.y * enu.y).sqrt())
+            .to_degrees();
+        (azimuth, elevation)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ecef_round_trip() {
+        let warsaw = Geodetic::new(52.2297, 21.0122, 110.0);
+        let ecef = warsaw.to_ecef();
+        assert!((ecef.norm() - 6_365_000.0).abs() < 10_000.0);
+        let back = Geodetic::from_ecef(ecef);
+        assert!((back.lat - warsaw.lat).abs() < 1e-9);
+        assert!((back.lon - warsaw.lon).abs() < 1e-9);
+        assert!((back.height - warsaw.height).abs() < 1e-4);
+    }
+
+    #[test]
+    fn look_angles() {
+        let receiver = Geodetic::new(45.0, 10.0, 0.0);
+        let zenith = Geodetic::new(45.0, 10.0, 20_000_000.0).to_ecef();
+        let (_, elevation) = receiver.look_angles(zenith);
+        assert!((elevation - 90.0).abs() < 1e-6);
+
+        let east = Geodetic::new(45.0, 11.0, 0.0).to_ecef();
+        let (azimuth, elevation) = receiver.look_angles(east);
+        assert!((azimuth - 90.0).abs() < 1.0);
+        assert!(elevation < 0.0);
+    }
+}
@@ -1,43 +1,171 @@
-use std::{collections::HashMap, f64::consts::PI, time::SystemTime};
+use std::{collections::HashMap, f64::consts::PI, sync::Arc};
 
 use nalgebra::Vector3;
 
-use crate::ublox::GpsSubframe;
+use crate::{
+    coords::Geodetic,
+    time_source::{SystemClock, TimeSource},
+    ublox::{
+        GnssId, GpsAlmanac, GpsAlmanacPage, GpsIonosphere, GpsSubframe, GpsUtc, UbloxMsg,
+        UbxNavPvt, UbxNavSat, UbxNavSatSv, UbxRxmRawx, UbxRxmSfrbx, UbxRxmSfrbxData,
+    },
+};
 
 #[derive(Debug, Clone)]
 pub struct GpsStatus {
+    clock: Arc<dyn TimeSource>,
     gps_time_correction: f64,
     satellites: HashMap<u8, SatelliteStatus>,
+    fix: Option<UbxNavPvt>,
+    ionosphere: Option<GpsIonosphere>,
+    utc: Option<GpsUtc>,
 }
 
 impl GpsStatus {
     pub fn new() -> Self {
+        Self::with_time_source(Arc::new(SystemClock))
+    }
+
+    /// Creates a status that tells the time between measurements using the given clock instead
+    /// of the system one - e.g. the clock of a replayed log.
+    pub fn with_time_source(clock: Arc<dyn TimeSource>) -> Self {
         Self {
+            clock,
             gps_time_correction: 0.0,
             satellites: Default::default(),
+            fix: None,
+            ionosphere: None,
+            utc: None,
         }
     }
 
-    fn unix_time() -> f64 {
-        SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap()
-            .as_secs_f64()
+    /// Forgets everything learned from the messages so far.
+    pub fn clear(&mut self) {
+        self.gps_time_correction = 0.0;
+        self.satellites.clear();
+        self.fix = None;
+        self.ionosphere = None;
+        self.utc = None;
     }
 
     pub fn set_time_correction(&mut self, current_gps_time: f64) {
-        self.gps_time_correction = current_gps_time - Self::unix_time();
+        self.gps_time_correction = current_gps_time - self.clock.now();
     }
 
     pub fn gps_time(&self) -> f64 {
-        Self::unix_time() + self.gps_time_correction
+        self.clock.now() + self.gps_time_correction
+    }
+
+    /// Updates the status with the information from a message, if it carries any.
+    pub fn consume_msg(&mut self, msg: &UbloxMsg) {
+        match msg {
+            UbloxMsg::RxmRawx(UbxRxmRawx { rcv_tow, week, .. }) => {
+                self.set_time_correction(*week as f64 * 604800.0 + rcv_tow);
+            }
+            UbloxMsg::RxmSfrbx(UbxRxmSfrbx {
+                sv_id,
+                data: UbxRxmSfrbxData::Gps(data),
+                ..
+            }) => {
+                let parity = &mut self.satellites.entry(*sv_id).or_default().parity;
+                parity.subframes += 1;
+                parity.failed_words += data.parity_errors.count_ones() as u64;
+                if data.parity_ok() {
+                    self.consume_subframe(*sv_id, data.subframe.clone());
+                } else {
+                    parity.rejected_subframes += 1;
+                }
+            }
+            UbloxMsg::NavPvt(pvt) => self.fix = Some(*pvt),
+            UbloxMsg::NavSat(nav_sat) => self.consume_nav_sat(nav_sat),
+            _ => {}
+        }
+    }
+
+    /// The latest navigation solution from the receiver, whether it has a valid fix or not.
+    pub fn fix(&self) -> Option<&UbxNavPvt> {
+        self.fix.as_ref()
+    }
+
+    /// The receiver position, if it has a valid fix.
+    pub fn receiver_position(&self) -> Option<Geodetic> {
+        self.fix
+            .filter(UbxNavPvt::has_fix)
+            .map(|pvt| Geodetic::new(pvt.lat, pvt.lon, pvt.height))
+    }
+
+    /// Replaces the receiver's view of the GPS satellites with the one from a NAV-SAT message.
+    pub fn consume_nav_sat(&mut self, nav_sat: &UbxNavSat) {
+        for status in self.satellites.values_mut() {
+            status.receiver_view = None;
+        }
+        for sv in nav_sat
+            .satellites
+            .iter()
+            .filter(|sv| sv.gnss_id == GnssId::Gps)
+        {
+            self.satellites.entry(sv.sv_id).or_default().receiver_view = Some(*sv);
+        }
+    }
+
+    pub fn satellite(&self, sv_id: u8) -> Option<&SatelliteStatus> {
+        self.satellites.get(&sv_id)
+    }
+
+    /// Azimuth and elevation of a satellite, in degrees, computed from its orbit and the receiver
+    /// position - to compare with the receiver's own view of the satellite. Uses the almanac if
+    /// the ephemeris isn't complete yet.
+    pub fn computed_look_angles(&self, sv_id: u8) -> Option<(f64, f64)> {
+        let receiver = self.receiver_position()?;
+        let elements = self.satellites.get(&sv_id)?.predicted_orbital_elements()?;
+        Some(receiver.look_angles(elements.position(self.gps_time())))
+    }
+
+    /// The Klobuchar coefficients from subframe 4, page 18.
+    pub fn ionosphere(&self) -> Option<&GpsIonosphere> {
+        self.ionosphere.as_ref()
+    }
+
+    /// The UTC parameters from subframe 4, page 18.
+    pub fn utc(&self) -> Option<&GpsUtc> {
+        self.utc.as_ref()
     }
 
     pub fn consume_subframe(&mut self, sv_id: u8, subframe: GpsSubframe) {
-        self.satellites
-            .entry(sv_id)
-            .or_default()
-            .consume_subframe(subframe);
+        match subframe {
+            // subframes 4 and 5 describe the whole constellation, not the transmitting satellite
+            GpsSubframe::Subframe4 { page, .. } | GpsSubframe::Subframe5 { page, .. } => {
+                self.consume_almanac_page(page)
+            }
+            subframe => self
+                .satellites
+                .entry(sv_id)
+                .or_default()
+                .consume_subframe(subframe),
+        }
+    }
+
+    fn consume_almanac_page(&mut self, page: GpsAlmanacPage) {
+        match page {
+            GpsAlmanacPage::Almanac(almanac) => {
+                self.satellites.entry(almanac.sv_id).or_default().almanac = Some(almanac);
+            }
+            GpsAlmanacPage::Health { sv_health, .. } => {
+                for (sv_id, health) in (1..).zip(sv_health) {
+                    self.satellites.entry(sv_id).or_default().health = Some(health);
+                }
+            }
+            GpsAlmanacPage::ConfigHealth { sv_health, .. } => {
+                for (sv_id, health) in (25..).zip(sv_health) {
+                    self.satellites.entry(sv_id).or_default().health = Some(health);
+                }
+            }
+            GpsAlmanacPage::IonoUtc { ionosphere, utc } => {
+                self.ionosphere = Some(ionosphere);
+                self.utc = Some(utc);
+            }
+            GpsAlmanacPage::Other { .. } => {}
+        }
     }
 
     pub fn complete_satellites(&self) -> impl Iterator<Item = (u8, SatelliteOrbitalElements)> + '_ {
@@ -45,16 +173,83 @@ impl GpsStatus {
             .iter()
             .filter_map(|(sv_id, status)| status.current_orbital_elements.map(|oe| (*sv_id, oe)))
     }
+
+    /// Satellites whose position can be predicted, from the ephemeris or from the almanac.
+    pub fn predicted_satellites(
+        &self,
+    ) -> impl Iterator<Item = (u8, SatelliteOrbitalElements)> + '_ {
+        self.satellites.iter().filter_map(|(sv_id, status)| {
+            status.predicted_orbital_elements().map(|oe| (*sv_id, oe))
+        })
+    }
+}
+
+impl Default for GpsStatus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parity check results of the subframes received from a satellite.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParityStats {
+    pub subframes: u64,
+    /// Subframes ignored because at least one of their words failed the check.
+    pub rejected_subframes: u64,
+    pub failed_words: u64,
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct SatelliteStatus {
     current_orbital_elements: Option<SatelliteOrbitalElements>,
     partial_subframe: Option<GpsSubframe>,
+    receiver_view: Option<UbxNavSatSv>,
+    almanac: Option<GpsAlmanac>,
+    health: Option<u8>,
+    parity: ParityStats,
+    clock: Option<SatelliteClock>,
 }
 
 impl SatelliteStatus {
+    pub fn orbital_elements(&self) -> Option<&SatelliteOrbitalElements> {
+        self.current_orbital_elements.as_ref()
+    }
+
+    pub fn almanac(&self) -> Option<&GpsAlmanac> {
+        self.almanac.as_ref()
+    }
+
+    /// The 6-bit health from the page 25 of subframe 4 or 5; 0 means all signals are OK.
+    pub fn health(&self) -> Option<u8> {
+        self.health
+    }
+
+    pub fn parity_stats(&self) -> &ParityStats {
+        &self.parity
+    }
+
+    /// The ephemeris, or the less precise almanac if there's no ephemeris yet.
+    pub fn predicted_orbital_elements(&self) -> Option<SatelliteOrbitalElements> {
+        self.current_orbital_elements.or_else(|| {
+            self.almanac
+                .as_ref()
+                .map(SatelliteOrbitalElements::from_almanac)
+        })
+    }
+
+    /// The satellite as reported in the latest NAV-SAT message, if it was listed there.
+    pub fn receiver_view(&self) -> Option<&UbxNavSatSv> {
+        self.receiver_view.as_ref()
+    }
+
     fn consume_subframe(&mut self, subframe: GpsSubframe) {
+        if let Some(clock) = SatelliteClock::from_subframe1(&subframe) {
+            if let Some(elements) = self.current_orbital_elements.as_mut() {
+                elements.set_clock(clock);
+            }
+            self.clock = Some(clock);
+            return;
+        }
         match (self.partial_subframe.take(), subframe) {
             (
                 Some(subframe2 @ GpsSubframe::Subframe2 { .. }),
@@ -63,12 +258,13 @@ impl SatelliteStatus {
             | (
                 Some(subframe3 @ GpsSubframe::Subframe3 { .. }),
                 subframe2 @ GpsSubframe::Subframe2 { .. },
-            ) => {
-                if subframe2.iode() == subframe3.iode() {
-                    let new_elements =
-                        SatelliteOrbitalElements::from_subframes(subframe2, subframe3);
-                    self.current_orbital_elements = Some(new_elements);
+            ) if subframe2.iode() == subframe3.iode() => {
+                let mut new_elements =
+                    SatelliteOrbitalElements::from_subframes(subframe2, subframe3);
+                if let Some(clock) = self.clock {
+                    new_elements.set_clock(clock);
                 }
+                self.current_orbital_elements = Some(new_elements);
             }
             (_, subframe @ GpsSubframe::Subframe2 { .. })
             | (_, subframe @ GpsSubframe::Subframe3 { .. }) => {
@@ -79,6 +275,83 @@ impl SatelliteStatus {
     }
 }
 
+/// WGS84 value of the Earth's gravitational constant, in m^3/s^2.
+const MU: f64 = 3.986005e14;
+
+/// Relativistic correction constant, in s/m^(1/2).
+const F: f64 = -4.442807633e-10;
+
+/// WGS84 value of the Earth's rotation rate, in rad/s.
+pub const OMEGA_E: f64 = 7.2921151467e-5;
+
+/// Where a satellite is and how it moves at a given time, in ECEF coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SatelliteState {
+    /// Meters.
+    pub position: Vector3<f64>,
+    /// Meters per second, in the rotating ECEF frame.
+    pub velocity: Vector3<f64>,
+    /// Satellite clock offset from GPS time in seconds, if the clock model is known.
+    pub clock_bias: Option<f64>,
+    /// Seconds per second.
+    pub clock_drift: Option<f64>,
+}
+
+/// The satellite clock model from subframe 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SatelliteClock {
+    pub iodc: u16,
+    pub toc: u32,
+    pub af0: f64,
+    pub af1: f64,
+    pub af2: f64,
+    /// L1-L2 group delay, in seconds.
+    pub tgd: f64,
+}
+
+impl SatelliteClock {
+    fn from_subframe1(subframe: &GpsSubframe) -> Option<Self> {
+        match *subframe {
+            GpsSubframe::Subframe1 {
+                iodc,
+                toc,
+                af0,
+                af1,
+                af2,
+                tgd,
+                ..
+            } => Some(SatelliteClock {
+                iodc,
+                toc,
+                af0,
+                af1,
+                af2,
+                tgd,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The polynomial part of the clock offset at the given GPS time, in seconds.
+    pub fn polynomial(&self, t: f64) -> f64 {
+        let dt = time_from_epoch(t, self.toc);
+        self.af0 + self.af1 * dt + self.af2 * dt * dt
+    }
+}
+
+/// Time in seconds from `epoch` (seconds of the week) to the GPS time `t`, accounting for the week
+/// crossover.
+fn time_from_epoch(t: f64, epoch: u32) -> f64 {
+    let dt = t % 604800.0 - epoch as f64;
+    if dt > 302400.0 {
+        dt - 604800.0
+    } else if dt < -302400.0 {
+        dt + 604800.0
+    } else {
+        dt
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct SatelliteOrbitalElements {
     m0: f64,
@@ -97,6 +370,8 @@ pub struct SatelliteOrbitalElements {
     c_ic: f64,
     c_is: f64,
     t_oe: u32,
+    iode: u8,
+    clock: Option<SatelliteClock>,
 }
 
 impl SatelliteOrbitalElements {
@@ -143,6 +418,8 @@ impl SatelliteOrbitalElements {
                 c_ic,
                 c_is,
                 t_oe,
+                iode: iode2,
+                clock: None,
             },
             (subframe2, subframe3) => panic!(
                 "wrong subframes passed to SatelliteOrbitalElements::from_subframes!\n\
@@ -152,37 +429,99 @@ impl SatelliteOrbitalElements {
         }
     }
 
-    pub fn position(&self, t: f64) -> Vector3<f64> {
-        let tow = t % 604800.0;
-        let mu = 3.986005e14;
-        let omega_e = 7.2921151467e-5;
-        let a = self.sqrt_a * self.sqrt_a;
-        let n0 = (mu / a.powi(3)).sqrt();
-        let mut tk = tow - self.t_oe as f64;
-        if tk > 302400.0 {
-            tk -= 604800.0;
-        } else if tk < -302400.0 {
-            tk += 604800.0;
+    /// Orbital elements with the precision of the almanac - without the perturbation terms.
+    pub fn from_almanac(almanac: &GpsAlmanac) -> Self {
+        SatelliteOrbitalElements {
+            m0: almanac.m0,
+            delta_n: 0.0,
+            e: almanac.e,
+            sqrt_a: almanac.sqrt_a,
+            omega0: almanac.omega0,
+            i0: 0.3 + almanac.delta_i,
+            omega_small: almanac.omega_small,
+            omega_dot: almanac.omega_dot,
+            i_dot: 0.0,
+            c_uc: 0.0,
+            c_us: 0.0,
+            c_rc: 0.0,
+            c_rs: 0.0,
+            c_ic: 0.0,
+            c_is: 0.0,
+            t_oe: almanac.toa,
+            iode: 0,
+            clock: Some(SatelliteClock {
+                iodc: 0,
+                toc: almanac.toa,
+                af0: almanac.af0,
+                af1: almanac.af1,
+                af2: 0.0,
+                tgd: 0.0,
+            }),
         }
-        let n = n0 + self.delta_n * PI;
+    }
+
+    /// Attaches the clock model if it belongs to the same data set (the low 8 bits of IODC
+    /// match IODE).
+    fn set_clock(&mut self, clock: SatelliteClock) {
+        if clock.iodc & 0xff == self.iode as u16 {
+            self.clock = Some(clock);
+        }
+    }
+
+    pub fn clock(&self) -> Option<&SatelliteClock> {
+        self.clock.as_ref()
+    }
+
+    /// The satellite clock offset from GPS time at the given GPS time, in seconds, including
+    /// the relativistic correction and the L1 group delay. `None` until the subframe 1 matching
+    /// the ephemeris has been received.
+    pub fn clock_bias(&self, t: f64) -> Option<f64> {
+        let clock = self.clock?;
+        let ecc_anomaly = self.eccentric_anomaly(time_from_epoch(t, self.t_oe));
+        Some(clock.polynomial(t) + self.relativistic_correction(ecc_anomaly) - clock.tgd)
+    }
+
+    fn relativistic_correction(&self, ecc_anomaly: f64) -> f64 {
+        F * self.e * self.sqrt_a * ecc_anomaly.sin()
+    }
+
+    /// Solves Kepler's equation for the eccentric anomaly at `tk` seconds from the ephemeris
+    /// reference time.
+    fn eccentric_anomaly(&self, tk: f64) -> f64 {
+        let a = self.sqrt_a * self.sqrt_a;
+        let n = (MU / a.powi(3)).sqrt() + self.delta_n * PI;
         let mk = self.m0 * PI + n * tk;
         let mut ecc_anomaly = mk;
-        loop {
-            let new_ecc_anomaly = ecc_anomaly
-                + (mk - ecc_anomaly - self.e * ecc_anomaly.sin())
-                    / (1.0 - self.e * ecc_anomaly.cos());
-            if ((new_ecc_anomaly - ecc_anomaly) / ecc_anomaly).abs() < 1e-9 {
+        for _ in 0..20 {
+            let step = (mk - ecc_anomaly + self.e * ecc_anomaly.sin())
+                / (1.0 - self.e * ecc_anomaly.cos());
+            ecc_anomaly += step;
+            if step.abs() < 1e-12 {
                 break;
             }
-            ecc_anomaly = new_ecc_anomaly;
         }
+        ecc_anomaly
+    }
+
+    /// The ECEF position of the satellite at the given GPS time.
+    pub fn position(&self, t: f64) -> Vector3<f64> {
+        self.state(t).position
+    }
+
+    /// The ECEF position and velocity of the satellite and its clock offset and drift at the
+    /// given GPS time.
+    pub fn state(&self, t: f64) -> SatelliteState {
+        let a = self.sqrt_a * self.sqrt_a;
+        let tk = time_from_epoch(t, self.t_oe);
+        let ecc_anomaly = self.eccentric_anomaly(tk);
         let true_anomaly =
             2.0 * (((1.0 + self.e) / (1.0 - self.e)).sqrt() * (ecc_anomaly / 2.0).tan()).atan();
 
         let phi_k = true_anomaly + self.omega_small * PI;
-        let delta_uk = self.c_us * (2.0 * phi_k).sin() + self.c_uc * (2.0 * phi_k).cos();
-        let delta_rk = self.c_rs * (2.0 * phi_k).sin() + self.c_rc * (2.0 * phi_k).cos();
-        let delta_ik = self.c_is * (2.0 * phi_k).sin() + self.c_ic * (2.0 * phi_k).cos();
+        let (sin_2phi, cos_2phi) = (2.0 * phi_k).sin_cos();
+        let delta_uk = self.c_us * sin_2phi + self.c_uc * cos_2phi;
+        let delta_rk = self.c_rs * sin_2phi + self.c_rc * cos_2phi;
+        let delta_ik = self.c_is * sin_2phi + self.c_ic * cos_2phi;
 
         let uk = phi_k + delta_uk;
         let rk = a * (1.0 - self.e * ecc_anomaly.cos()) + delta_rk;
@@ -191,12 +530,119 @@ impl SatelliteOrbitalElements {
         let ykprim = rk * uk.sin();
 
         let omega_k =
-            self.omega0 * PI + (self.omega_dot * PI - omega_e) * tk - omega_e * self.t_oe as f64;
+            self.omega0 * PI + (self.omega_dot * PI - OMEGA_E) * tk - OMEGA_E * self.t_oe as f64;
 
         let xk = xkprim * omega_k.cos() - ykprim * omega_k.sin() * ik.cos();
         let yk = xkprim * omega_k.sin() + ykprim * omega_k.cos() * ik.cos();
         let zk = ykprim * ik.sin();
 
-        Vector3::new(xk, zk, -yk) // we're using a coordinate system where Y is along the Earth's axis
+        // time derivatives of the above
+        let n = (MU / a.powi(3)).sqrt() + self.delta_n * PI;
+        let ecc_anomaly_dot = n / (1.0 - self.e * ecc_anomaly.cos());
+        let true_anomaly_dot =
+            ecc_anomaly_dot * (1.0 - self.e * self.e).sqrt() / (1.0 - self.e * ecc_anomaly.cos());
+        let uk_dot = true_anomaly_dot * (1.0 + 2.0 * (self.c_us * cos_2phi - self.c_uc * sin_2phi));
+        let rk_dot = a * self.e * ecc_anomaly.sin() * ecc_anomaly_dot
+            + 2.0 * true_anomaly_dot * (self.c_rs * cos_2phi - self.c_rc * sin_2phi);
+        let ik_dot = self.i_dot * PI
+            + 2.0 * true_anomaly_dot * (self.c_is * cos_2phi - self.c_ic * sin_2phi);
+        let omega_k_dot = self.omega_dot * PI - OMEGA_E;
+
+        let xkprim_dot = rk_dot * uk.cos() - rk * uk_dot * uk.sin();
+        let ykprim_dot = rk_dot * uk.sin() + rk * uk_dot * uk.cos();
+
+        let (sin_omega, cos_omega) = omega_k.sin_cos();
+        let (sin_i, cos_i) = ik.sin_cos();
+        let vx = xkprim_dot * cos_omega
+            - ykprim_dot * sin_omega * cos_i
+            - xkprim * omega_k_dot * sin_omega
+            - ykprim * (omega_k_dot * cos_omega * cos_i - ik_dot * sin_omega * sin_i);
+        let vy = xkprim_dot * sin_omega
+            + ykprim_dot * cos_omega * cos_i
+            + xkprim * omega_k_dot * cos_omega
+            - ykprim * (omega_k_dot * sin_omega * cos_i + ik_dot * cos_omega * sin_i);
+        let vz = ykprim_dot * sin_i + ykprim * ik_dot * cos_i;
+
+        let clock = self.clock.map(|clock| {
+            let dt = time_from_epoch(t, clock.toc);
+            let relativistic_dot = F * self.e * self.sqrt_a * ecc_anomaly.cos() * ecc_anomaly_dot;
+            (
+                clock.polynomial(t) + self.relativistic_correction(ecc_anomaly) - clock.tgd,
+                clock.af1 + 2.0 * clock.af2 * dt + relativistic_dot,
+            )
+        });
+
+        SatelliteState {
+            position: Vector3::new(xk, yk, zk),
+            velocity: Vector3::new(vx, vy, vz),
+            clock_bias: clock.map(|(bias, _)| bias),
+            clock_drift: clock.map(|(_, drift)| drift),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn subframe1(iodc: u16, af0: f64) -> GpsSubframe {
+        GpsSubframe::Subframe1 {
+            week_number: 100,
+            ura_index: 0,
+            sv_health: 0,
+            tgd: 5e-9,
+            iodc,
+            toc: 7200,
+            af2: 0.0,
+            af1: 1e-11,
+            af0,
+        }
+    }
+
+    #[test]
+    fn ephemeris_clock() {
+        let mut status = SatelliteStatus::default();
+        status.consume_subframe(subframe1(0x105, 1e-4));
+        status.consume_subframe(GpsSubframe::Subframe2 {
+            aodo: 0,
+            iode: 5,
+            c_rs: 0.0,
+            delta_n: 0.0,
+            m0: 0.25,
+            c_uc: 0.0,
+            e: 0.02,
+            sqrt_a: 5153.6,
+            c_us: 0.0,
+            t_oe: 7200,
+        });
+        status.consume_subframe(GpsSubframe::Subframe3 {
+            iode: 5,
+            c_ic: 0.0,
+            omega0: 0.0,
+            c_is: 0.0,
+            i0: 0.3,
+            c_rc: 0.0,
+            omega_small: 0.0,
+            omega_dot: 0.0,
+            i_dot: 0.0,
+        });
+        // a clock from another data set doesn't replace the matching one
+        status.consume_subframe(subframe1(0x006, 2e-4));
+
+        let elements = status.orbital_elements().unwrap();
+        let t = 604800.0 * 100.0 + 7300.0;
+        let ecc_anomaly = elements.eccentric_anomaly(100.0);
+        let n = (MU / 5153.6_f64.powi(6)).sqrt();
+        let mean_anomaly = 0.25 * PI + n * 100.0;
+        assert!((ecc_anomaly - 0.02 * ecc_anomaly.sin() - mean_anomaly).abs() < 1e-12);
+
+        let expected = 1e-4 + 1e-11 * 100.0 + F * 0.02 * 5153.6 * ecc_anomaly.sin() - 5e-9;
+        assert!((elements.clock_bias(t).unwrap() - expected).abs() < 1e-15);
+
+        let state = elements.state(t);
+        let (before, after) = (elements.state(t - 0.5), elements.state(t + 0.5));
+        assert!(((after.position - before.position) - state.velocity).norm() < 1e-3);
+        let drift = after.clock_bias.unwrap() - before.clock_bias.unwrap();
+        assert!((drift - state.clock_drift.unwrap()).abs() < 1e-15);
     }
 }
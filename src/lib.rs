@@ -0,0 +1,17 @@
+//! u-blox receiver tooling: the UBX protocol codec, a stream framer demultiplexing UBX and NMEA
+//! messages, and a model of the GPS constellation built from the broadcast navigation data.
+//!
+//! The `gps-util` command line tool is built on top of this library. Its OpenGL visualizer is
+//! only compiled with the `visualizer` feature (enabled by default).
+
+pub mod config;
+pub mod coords;
+pub mod gps_status;
+pub mod port_buffer;
+pub mod positioning;
+pub mod profile;
+pub mod receiver_info;
+pub mod recorder;
+pub mod replay;
+pub mod time_source;
+pub mod ublox;
@@ -1,170 +1,585 @@
-mod gps_status;
-mod port_buffer;
+mod cli;
+#[cfg(feature = "visualizer")]
 mod renderer;
-mod ublox;
+#[cfg(feature = "visualizer")]
+mod view;
 
 use std::{
-    sync::{Arc, RwLock},
-    thread,
-    time::{Duration, Instant},
+    error::Error,
+    fs::File,
+    io::{self, Read, Write},
+    path::Path,
+    time::Duration,
 };
 
-use glium::{
-    glutin::{
-        event::{ElementState, Event, VirtualKeyCode, WindowEvent},
-        event_loop::{ControlFlow, EventLoop},
-        window::WindowBuilder,
-        ContextBuilder,
+use clap::Parser;
+use gps_util::{
+    config::{compare_config_items, compare_legacy_config, read_receiver_config, ReceiverConfig},
+    coords::Geodetic,
+    gps_status::GpsStatus,
+    port_buffer::*,
+    positioning::{
+        self, forecast_dop, solve_with_raim, DopSample, Exclusion, FaultTest, PositionSolution,
+        RaimSettings, SppSettings,
+    },
+    receiver_info::{ConfigInterface, ReceiverInfo},
+    recorder::Recorder,
+    replay::{Pacing, Replay, ReplayEvent},
+    time_source::TimeSource,
+    ublox::{
+        CfgLayer, CfgLayers, ChecksumMode, UbloxMsg, UbxCfgCfg, UbxCfgGnss, UbxCfgRst,
+        UbxCfgRstMode, UbxCfgRstStart, UbxMonVer,
     },
-    Display,
-};
-use serialport::{self};
-
-use gps_status::GpsStatus;
-use port_buffer::*;
-use renderer::Renderer;
-use ublox::{
-    GnssId, UbloxMsg, UbxCfgGnss, UbxCfgMsg, UbxCfgPrt, UbxCfgPrtUsbInMask, UbxCfgPrtUsbOutMask,
-    UbxCfgRate, UbxCfgRateTimeRef, UbxRxmRawx, UbxRxmSfrbx, UbxRxmSfrbxData, UbxRxmSfrbxDataGps,
 };
+use serialport::{self, SerialPort};
 
-fn port_thread(gps_status: Arc<RwLock<GpsStatus>>) {
-    let serial = serialport::new("/dev/ttyACM0", 9600)
-        .timeout(Duration::from_secs(10))
-        .open_native()
-        .unwrap();
+use cli::{Command, Opts, PortOpts};
 
-    let mut port = PortBuffer::new(serial);
+fn open_port(opts: &PortOpts) -> Result<PortBuffer<Box<dyn SerialPort>>, Box<dyn Error>> {
+    let serial = serialport::new(&opts.device, opts.baud)
+        .timeout(Duration::from_millis(100))
+        .open()
+        .map_err(|err| format!("couldn't open {}: {}", opts.device, err))?;
+    Ok(PortBuffer::new(serial))
+}
 
-    port.send(Message::Ublox(UbloxMsg::CfgPrt(UbxCfgPrt::SetUsb {
-        in_mask: UbxCfgPrtUsbInMask::UBX,
-        out_mask: UbxCfgPrtUsbOutMask::UBX,
-    })));
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(1);
+const REQUEST_RETRIES: usize = 2;
 
-    port.send(Message::Ublox(UbloxMsg::CfgRate(UbxCfgRate {
-        meas_rate_ms: 1000,
-        nav_rate_cycles: 1,
-        time_ref: UbxCfgRateTimeRef::Gps,
-    })));
+fn query_receiver<P: Read + Write>(port: &mut PortBuffer<P>) -> Result<ReceiverInfo, RequestError> {
+    let poll = UbloxMsg::MonVer(UbxMonVer::Poll);
+    match port.poll(poll, REQUEST_TIMEOUT, REQUEST_RETRIES)? {
+        UbloxMsg::MonVer(version) => {
+            ReceiverInfo::from_mon_ver(&version).ok_or(RequestError::UnexpectedResponse {
+                class: UbxMonVer::CLASS,
+                id: UbxMonVer::ID,
+            })
+        }
+        _ => unreachable!("poll only returns messages of the polled type"),
+    }
+}
 
-    port.send(Message::Ublox(UbloxMsg::CfgMsg(UbxCfgMsg::SetRate {
-        class: 0x02,
-        id: 0x13,
-        rate: 1,
-    })));
+/// Queries the receiver and prints what it is and how its settings differ from `config`.
+fn check_receiver<P: Read + Write>(
+    port: &mut PortBuffer<P>,
+    config: &ReceiverConfig,
+) -> Result<ReceiverInfo, Box<dyn Error>> {
+    let info = query_receiver(port)?;
+    println!(
+        "Receiver: {:?} {}, protocol {:?}",
+        info.generation,
+        info.firmware.as_deref().unwrap_or(&info.sw_version),
+        info.protocol_version
+    );
+    for gnss in config.gnss.iter().filter(|gnss| !info.supports(**gnss)) {
+        println!("Warning: the receiver doesn't support {}", gnss);
+    }
 
-    port.send(Message::Ublox(UbloxMsg::CfgMsg(UbxCfgMsg::SetRate {
-        class: 0x02,
-        id: 0x15,
-        rate: 1,
-    })));
+    let differences = match info.config_interface() {
+        ConfigInterface::Legacy => {
+            compare_legacy_config(port, config, REQUEST_TIMEOUT, REQUEST_RETRIES)?
+        }
+        ConfigInterface::ValueDatabase => {
+            let items = config.config_items()?;
+            compare_config_items(port, &items, REQUEST_TIMEOUT, REQUEST_RETRIES)?
+        }
+    };
+    if differences.is_empty() {
+        println!("The receiver's settings match the configuration");
+    } else {
+        println!("Settings to change:");
+        for difference in differences {
+            println!("  {}", difference);
+        }
+    }
+    Ok(info)
+}
 
-    port.send(Message::Ublox(UbloxMsg::CfgGnss(UbxCfgGnss::Poll)));
+/// Sends the configuration to the receiver, checking that every message gets acknowledged.
+/// Returns what the receiver reported about itself.
+fn configure_receiver<P: Read + Write>(
+    port: &mut PortBuffer<P>,
+    config: &ReceiverConfig,
+) -> Result<ReceiverInfo, Box<dyn Error>> {
+    let info = check_receiver(port, config)?;
 
-    loop {
-        if let Err(err) = port.read() {
-            println!("Error! {}\n", err);
-            continue;
+    if info.config_interface() == ConfigInterface::ValueDatabase {
+        for msg in config.valset_messages(CfgLayers::RAM)? {
+            port.send_and_wait_ack(msg, REQUEST_TIMEOUT, REQUEST_RETRIES)?;
         }
-        let msg = port.read_msg();
-        if let Some(ref msg) = msg {
-            println!("{:#?}", msg);
+        return Ok(info);
+    }
+
+    for msg in config.startup_messages() {
+        if let UbloxMsg::CfgGnss(UbxCfgGnss::Poll) = msg {
+            if let UbloxMsg::CfgGnss(current) = port.poll(msg, REQUEST_TIMEOUT, REQUEST_RETRIES)? {
+                if let Some(settings) = config.gnss_settings(current) {
+                    let msg = UbloxMsg::CfgGnss(settings);
+                    println!("Sending {:#?}\n", msg);
+                    port.send_and_wait_ack(msg, REQUEST_TIMEOUT, REQUEST_RETRIES)?;
+                }
+            }
+        } else {
+            port.send_and_wait_ack(msg, REQUEST_TIMEOUT, REQUEST_RETRIES)?;
         }
+    }
+    Ok(info)
+}
+
+fn print_messages<P: Read + Write>(port: &mut PortBuffer<P>) {
+    while let Some(msg) = port.read_msg() {
         match msg {
-            None => {}
-            Some(Message::Ublox(UbloxMsg::CfgGnss(UbxCfgGnss::Settings {
-                version,
-                num_trk_ch_hw,
-                num_trk_ch_use,
-                config_blocks,
-            }))) => {
-                let config_blocks = config_blocks
-                    .into_iter()
-                    .map(|mut block| {
-                        if block.gnss_id != GnssId::Gps {
-                            block.enabled = false;
-                        } else {
-                            block.res_trk_ch = block.max_trk_ch;
-                        }
-                        block
-                    })
-                    .collect();
-                let msg = UbloxMsg::CfgGnss(UbxCfgGnss::Settings {
-                    version,
-                    num_trk_ch_hw,
-                    num_trk_ch_use,
-                    config_blocks,
-                });
-                println!("Sending {:#?}\n", msg);
-                port.send(Message::Ublox(msg));
+            Ok(Message::Ublox(msg)) => {
+                println!("{}: {:#?}", msg.name().unwrap_or("unknown"), msg);
             }
-            Some(Message::Ublox(UbloxMsg::RxmRawx(UbxRxmRawx { rcv_tow, week, .. }))) => {
-                gps_status
-                    .write()
-                    .unwrap()
-                    .set_time_correction(week as f64 * 604800.0 + rcv_tow);
+            Ok(Message::Nmea(nmea)) => print!("{}", nmea),
+            Err(err) => println!("Skipped invalid data: {}", err),
+        }
+    }
+}
+
+fn checksum_mode(lenient: bool) -> ChecksumMode {
+    if lenient {
+        ChecksumMode::Lenient
+    } else {
+        ChecksumMode::Strict
+    }
+}
+
+fn decode(file: Option<&Path>, port_opts: &PortOpts, lenient: bool) -> Result<(), Box<dyn Error>> {
+    let checksum_mode = checksum_mode(lenient);
+    match file {
+        Some(file) => {
+            let mut port = PortBuffer::new(Duplex::new(File::open(file)?, io::sink()));
+            port.set_checksum_mode(checksum_mode);
+            while port.read()? > 0 {
+                print_messages(&mut port);
             }
-            Some(Message::Ublox(UbloxMsg::RxmSfrbx(UbxRxmSfrbx {
-                sv_id,
-                data: UbxRxmSfrbxData::Gps(UbxRxmSfrbxDataGps { subframe, .. }),
-                ..
-            }))) => {
-                gps_status
-                    .write()
-                    .unwrap()
-                    .consume_subframe(sv_id, subframe);
+            println!("{:#?}", port.stats());
+        }
+        None => {
+            let mut port = open_port(port_opts)?;
+            port.set_checksum_mode(checksum_mode);
+            loop {
+                port.read()?;
+                print_messages(&mut port);
             }
-            _ => {}
         }
     }
+    Ok(())
 }
 
-fn main() {
-    let event_loop = EventLoop::new();
+fn record(
+    file: &Path,
+    index: bool,
+    port_opts: &PortOpts,
+    config: Option<&ReceiverConfig>,
+) -> Result<(), Box<dyn Error>> {
+    let mut port = open_port(port_opts)?;
+    port.set_recorder(Some(Recorder::create(file, index)?));
+    if let Some(config) = config {
+        configure_receiver(&mut port, config)?;
+    }
+
+    println!("Recording to {}", file.display());
+    loop {
+        port.read()?;
+        while let Some(msg) = port.read_msg() {
+            match msg {
+                Ok(Message::Ublox(msg)) => println!("{}", msg.name().unwrap_or("unknown")),
+                Ok(Message::Nmea(_)) => {}
+                Err(err) => println!("Invalid data: {}", err),
+            }
+        }
+    }
+}
+
+fn print_replay(mut replay: Replay<File>) -> Result<(), Box<dyn Error>> {
+    let clock = replay.clock();
+    while let Some(event) = replay.next_event()? {
+        match event {
+            ReplayEvent::Message(Message::Ublox(msg)) => {
+                println!(
+                    "[{:.3}] {}: {:#?}",
+                    clock.now(),
+                    msg.name().unwrap_or("unknown"),
+                    msg
+                );
+            }
+            ReplayEvent::Message(Message::Nmea(nmea)) => print!("{}", nmea),
+            ReplayEvent::Error(err) => println!("Skipped invalid data: {}", err),
+            ReplayEvent::Rewound => {}
+        }
+    }
+    println!("{:#?}", replay.stats());
+    Ok(())
+}
+
+/// Prints our own solution for every RXM-RAWX in a log, and how far the receiver's solution for
+/// the same epoch is from it.
+fn solve(file: &Path, lenient: bool, settings: &SppSettings) -> Result<(), Box<dyn Error>> {
+    let mut replay = Replay::open(file, Pacing::AsFastAsPossible)?;
+    replay.set_checksum_mode(checksum_mode(lenient));
+    let mut gps_status = GpsStatus::with_time_source(replay.clock());
+    let raim = RaimSettings::default();
+    let mut last_solution: Option<PositionSolution> = None;
+    while let Some(event) = replay.next_event()? {
+        let msg = match event {
+            ReplayEvent::Message(Message::Ublox(msg)) => msg,
+            ReplayEvent::Error(err) => {
+                println!("Skipped invalid data: {}", err);
+                continue;
+            }
+            _ => continue,
+        };
+        gps_status.consume_msg(&msg);
+        match msg {
+            UbloxMsg::RxmRawx(raw) => match solve_with_raim(&raw, &gps_status, settings, &raim) {
+                Ok(result) => {
+                    let solution = &result.solution;
+                    let geodetic = solution.geodetic();
+                    println!(
+                        "[{:.3}] {:.8} {:.8} {:.2} m, clock {:.2} m, {} satellites, PDOP {:.1}",
+                        raw.rcv_tow,
+                        geodetic.lat,
+                        geodetic.lon,
+                        geodetic.height,
+                        solution.clock_bias,
+                        solution.satellites.len(),
+                        solution.dop().map_or(f64::NAN, |dop| dop.pdop)
+                    );
+                    print_raim(&result.exclusions, result.test);
+                    if let Some(velocity) = &solution.velocity {
+                        let ned = velocity.ned(&geodetic);
+                        println!(
+                            "    velocity: north {:.3} m/s, east {:.3} m/s, down {:.3} m/s, clock drift {:.3} m/s",
+                            ned.x, ned.y, ned.z, velocity.clock_drift
+                        );
+                    }
+                    last_solution = Some(result.solution);
+                }
+                Err(err) => {
+                    println!("[{:.3}] no solution: {}", raw.rcv_tow, err);
+                    last_solution = None;
+                }
+            },
+            UbloxMsg::NavPvt(pvt) if pvt.has_fix() => {
+                let solution = match &last_solution {
+                    Some(solution) => solution,
+                    None => continue,
+                };
+                let tow_ms = (solution.time % 604800.0) * 1000.0;
+                if (tow_ms - pvt.itow as f64).abs() > 100.0 {
+                    continue;
+                }
+                let receiver = Geodetic::new(pvt.lat, pvt.lon, pvt.height);
+                let offset = receiver.to_enu(solution.position);
+                println!(
+                    "    from the receiver's solution: east {:.2} m, north {:.2} m, up {:.2} m",
+                    offset.x, offset.y, offset.z
+                );
+                if let Some(velocity) = &solution.velocity {
+                    let ned = velocity.ned(&receiver);
+                    println!(
+                        "    velocity difference: north {:.3} m/s, east {:.3} m/s, down {:.3} m/s",
+                        ned.x - pvt.vel_north,
+                        ned.y - pvt.vel_east,
+                        ned.z - pvt.vel_down
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+    println!("{:#?}", replay.stats());
+    Ok(())
+}
 
-    let wb = WindowBuilder::new().with_title("GPS Visualization");
-    let cb = ContextBuilder::new();
-    let display = Display::new(wb, cb, &event_loop).unwrap();
+fn print_raim(exclusions: &[Exclusion], test: Option<FaultTest>) {
+    for exclusion in exclusions {
+        println!("    {}", exclusion);
+    }
+    match test {
+        Some(test) => println!(
+            "    RAIM {}: test statistic {:.1}, threshold {:.1}, HPL {:.1} m, VPL {:.1} m",
+            if test.fault_detected() {
+                "fault detected"
+            } else {
+                "passed"
+            },
+            test.test_statistic,
+            test.threshold,
+            test.hpl,
+            test.vpl
+        ),
+        None => println!("    RAIM unavailable: 5 satellites needed"),
+    }
+}
 
-    let mut renderer = Renderer::new(&display);
+fn format_gps_time(t: f64) -> String {
+    const DAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    let week = (t / 604800.0).floor();
+    let minutes = ((t - week * 604800.0) / 60.0).round() as usize;
+    format!(
+        "week {} {} {:02}:{:02}",
+        week,
+        DAYS[minutes / 1440 % 7],
+        minutes / 60 % 24,
+        minutes % 60
+    )
+}
 
-    let gps_status = Arc::new(RwLock::new(GpsStatus::new()));
-    let gps_status_clone = gps_status.clone();
-    let _port_thread = thread::spawn(move || port_thread(gps_status_clone));
+/// Forecasts the DOP at a site, for its own position or where the receiver was in the log. With
+/// `max_pdop`, only prints the periods when the PDOP doesn't exceed it.
+fn forecast(
+    file: &Path,
+    site: Option<Geodetic>,
+    hours: f64,
+    step_minutes: f64,
+    elevation_mask: f64,
+    max_pdop: Option<f64>,
+    lenient: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut replay = Replay::open(file, Pacing::AsFastAsPossible)?;
+    replay.set_checksum_mode(checksum_mode(lenient));
+    let mut gps_status = GpsStatus::with_time_source(replay.clock());
+    let settings = SppSettings {
+        elevation_mask,
+        ..SppSettings::default()
+    };
+    let mut solution = None;
+    while let Some(event) = replay.next_event()? {
+        if let ReplayEvent::Message(Message::Ublox(msg)) = event {
+            gps_status.consume_msg(&msg);
+            if let UbloxMsg::RxmRawx(raw) = &msg {
+                solution = positioning::solve(raw, &gps_status, &settings)
+                    .ok()
+                    .or(solution);
+            }
+        }
+    }
 
-    let start = Instant::now();
+    let site = site
+        .or_else(|| gps_status.receiver_position())
+        .or_else(|| solution.as_ref().map(PositionSolution::geodetic))
+        .ok_or("the log doesn't have a position fix, give the site with --lat and --lon")?;
+    let samples = forecast_dop(
+        &gps_status,
+        &site,
+        gps_status.gps_time(),
+        hours * 3600.0,
+        step_minutes * 60.0,
+        elevation_mask,
+    )?;
+    let below_limit = |sample: &DopSample| match (sample.dop, max_pdop) {
+        (Some(dop), Some(max_pdop)) => dop.pdop <= max_pdop,
+        _ => false,
+    };
 
-    // event handling loop (in main thread)
-    event_loop.run(move |ev, _, control_flow| {
-        match ev {
-            Event::WindowEvent { event, .. } => match event {
-                WindowEvent::CloseRequested => {
-                    *control_flow = ControlFlow::Exit;
-                    return;
+    match max_pdop {
+        None => {
+            for sample in samples {
+                match sample.dop {
+                    Some(dop) => println!(
+                        "{}  {:2} satellites  PDOP {:5.2}  HDOP {:5.2}  VDOP {:5.2}",
+                        format_gps_time(sample.time),
+                        sample.satellites.len(),
+                        dop.pdop,
+                        dop.hdop,
+                        dop.vdop
+                    ),
+                    None => println!(
+                        "{}  {:2} satellites  no solution",
+                        format_gps_time(sample.time),
+                        sample.satellites.len()
+                    ),
                 }
-                WindowEvent::KeyboardInput { input, .. } => {
-                    match (input.state, input.virtual_keycode) {
-                        (ElementState::Pressed, Some(VirtualKeyCode::Space)) => {
-                            // TODO
-                        }
-                        _ => (),
+            }
+        }
+        Some(max_pdop) => {
+            let mut start = None;
+            for (i, sample) in samples.iter().enumerate() {
+                match (start, below_limit(sample)) {
+                    (None, true) => start = Some(sample.time),
+                    (Some(from), false) => {
+                        println!(
+                            "{} - {}",
+                            format_gps_time(from),
+                            format_gps_time(samples[i - 1].time)
+                        );
+                        start = None;
                     }
+                    _ => {}
                 }
-                _ => return,
-            },
-            Event::MainEventsCleared => {
-                let t = start.elapsed().as_secs_f32();
-                let gps_t = gps_status.read().unwrap().gps_time();
-                let satellites: Vec<_> = gps_status
-                    .read()
-                    .unwrap()
-                    .complete_satellites()
-                    .map(|(sv_id, orb_elem)| (sv_id, orb_elem.position(gps_t)))
-                    .collect();
-                renderer.draw(&display, t, satellites);
             }
-            _ => (),
+            if let (Some(from), Some(last)) = (start, samples.last()) {
+                println!("{} - {}", format_gps_time(from), format_gps_time(last.time));
+            }
+            if samples.iter().all(|sample| !below_limit(sample)) {
+                println!("PDOP never gets down to {}", max_pdop);
+            }
         }
-        *control_flow = ControlFlow::Poll;
-    });
+    }
+    Ok(())
+}
+
+fn configure(
+    port_opts: &PortOpts,
+    config: &ReceiverConfig,
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut port = open_port(port_opts)?;
+    if dry_run {
+        check_receiver(&mut port, config)?;
+    } else {
+        configure_receiver(&mut port, config)?;
+    }
+    Ok(())
+}
+
+fn save_config(port_opts: &PortOpts) -> Result<(), Box<dyn Error>> {
+    let mut port = open_port(port_opts)?;
+    let msg = UbloxMsg::CfgCfg(UbxCfgCfg::save(None));
+    port.send_and_wait_ack(msg, REQUEST_TIMEOUT, REQUEST_RETRIES)?;
+    println!("Configuration saved");
+    Ok(())
+}
+
+fn revert_config(port_opts: &PortOpts) -> Result<(), Box<dyn Error>> {
+    let mut port = open_port(port_opts)?;
+    let msg = UbloxMsg::CfgCfg(UbxCfgCfg::revert(None));
+    port.send_and_wait_ack(msg, REQUEST_TIMEOUT, REQUEST_RETRIES)?;
+    println!("Default configuration restored");
+    Ok(())
+}
+
+fn reset(
+    port_opts: &PortOpts,
+    start: UbxCfgRstStart,
+    mode: UbxCfgRstMode,
+) -> Result<(), Box<dyn Error>> {
+    let mut port = open_port(port_opts)?;
+    // CFG-RST is never acknowledged - the receiver just goes down
+    port.send(Message::Ublox(UbloxMsg::CfgRst(UbxCfgRst::new(
+        start, mode,
+    ))))?;
+    println!("Reset sent: {:?} start, {:?} reset", start, mode);
+    Ok(())
+}
+
+fn dump_config(port_opts: &PortOpts, layer: CfgLayer) -> Result<(), Box<dyn Error>> {
+    let mut port = open_port(port_opts)?;
+    for item in read_receiver_config(&mut port, layer, REQUEST_TIMEOUT, REQUEST_RETRIES)? {
+        println!("{}", item);
+    }
+    Ok(())
+}
+
+fn info(port_opts: &PortOpts) -> Result<(), Box<dyn Error>> {
+    let mut port = open_port(port_opts)?;
+    println!("{:#?}", query_receiver(&mut port)?);
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let opts = Opts::parse();
+
+    match opts.command {
+        #[cfg(feature = "visualizer")]
+        Command::View {
+            port,
+            config,
+            no_configure,
+            record,
+        } => {
+            let mut port = open_port(&port)?;
+            if let Some(file) = record.record {
+                port.set_recorder(Some(Recorder::create(file, record.index)?));
+            }
+            let config = if no_configure {
+                None
+            } else {
+                Some(config.receiver_config()?)
+            };
+            view::run(port, config)
+        }
+        Command::Record {
+            file,
+            index,
+            port,
+            config,
+            no_configure,
+        } => {
+            let config = if no_configure {
+                None
+            } else {
+                Some(config.receiver_config()?)
+            };
+            record(&file, index, &port, config.as_ref())
+        }
+        Command::Replay {
+            file,
+            speed,
+            fast,
+            lenient,
+            #[cfg(feature = "visualizer")]
+            no_view,
+        } => {
+            let pacing = if fast {
+                Pacing::AsFastAsPossible
+            } else {
+                Pacing::RealTime
+            };
+            let mut replay = Replay::open(&file, pacing)?;
+            replay.set_checksum_mode(checksum_mode(lenient));
+            replay.clock().set_speed(speed);
+            #[cfg(feature = "visualizer")]
+            if !no_view {
+                view::replay(replay);
+            }
+            print_replay(replay)
+        }
+        Command::Decode {
+            file,
+            port,
+            lenient,
+        } => decode(file.as_deref(), &port, lenient),
+        Command::Solve {
+            file,
+            elevation_mask,
+            lenient,
+        } => {
+            let settings = SppSettings {
+                elevation_mask,
+                ..SppSettings::default()
+            };
+            solve(&file, lenient, &settings)
+        }
+        Command::Forecast {
+            file,
+            lat,
+            lon,
+            height,
+            hours,
+            step,
+            elevation_mask,
+            max_pdop,
+            lenient,
+        } => {
+            let site = lat
+                .zip(lon)
+                .map(|(lat, lon)| Geodetic::new(lat, lon, height));
+            forecast(&file, site, hours, step, elevation_mask, max_pdop, lenient)
+        }
+        Command::Configure {
+            port,
+            config,
+            dry_run,
+        } => configure(&port, &config.receiver_config()?, dry_run),
+        Command::Save { port } => save_config(&port),
+        Command::Revert { port } => revert_config(&port),
+        Command::Reset { port, start, mode } => reset(&port, start, mode),
+        Command::Info { port } => info(&port),
+        Command::DumpConfig { port, layer } => dump_config(&port, layer),
+    }
 }
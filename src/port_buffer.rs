@@ -1,12 +1,17 @@
 use std::{
-    convert::TryInto,
-    io::{Read, Write},
+    collections::VecDeque,
+    convert::{TryFrom, TryInto},
+    error::Error,
+    fmt,
+    io::{self, ErrorKind, Read, Write},
     mem,
+    time::{Duration, Instant},
 };
 
-use serialport::{self, SerialPort, TTYPort};
-
-use crate::ublox::UbloxMsg;
+use crate::{
+    recorder::Recorder,
+    ublox::{ChecksumMode, UbloxMsg, UbloxRawMsg, UbxAckNak, UbxError},
+};
 
 #[derive(Debug, Clone)]
 pub enum Message {
@@ -14,80 +19,694 @@ pub enum Message {
     Nmea(String),
 }
 
+/// UBX payloads longer than this are assumed to be a false sync inside binary data.
+pub const MAX_UBX_PAYLOAD: usize = 8192;
+/// NMEA 0183 limits sentences to 82 characters; anything well beyond that isn't a sentence.
+pub const MAX_NMEA_LENGTH: usize = 128;
+/// The buffer never holds more than this many bytes - the oldest bytes are dropped first.
+pub const MAX_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Recoverable framing problems. The offending bytes are skipped and the framer resynchronizes
+/// on the next sync sequence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FramerError {
+    /// A complete UBX frame that failed to decode.
+    Ubx(UbxError),
+    /// Something that started like an NMEA sentence but wasn't printable ASCII, had no line
+    /// terminator within `MAX_NMEA_LENGTH` bytes or had a wrong checksum.
+    InvalidNmea(Vec<u8>),
+    /// A UBX header declaring a payload longer than `MAX_UBX_PAYLOAD`.
+    OversizedFrame { class: u8, id: u8, length: usize },
+    /// The buffer grew over `MAX_BUFFER_SIZE` and this many bytes had to be discarded.
+    BufferOverflow { dropped: usize },
+}
+
+impl From<UbxError> for FramerError {
+    fn from(err: UbxError) -> Self {
+        FramerError::Ubx(err)
+    }
+}
+
+impl fmt::Display for FramerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FramerError::Ubx(err) => write!(f, "{}", err),
+            FramerError::InvalidNmea(bytes) => write!(
+                f,
+                "invalid NMEA sentence: {:?}",
+                String::from_utf8_lossy(bytes)
+            ),
+            FramerError::OversizedFrame { class, id, length } => write!(
+                f,
+                "0x{:02x} 0x{:02x}: declared payload length {} exceeds the maximum of {}",
+                class, id, length, MAX_UBX_PAYLOAD
+            ),
+            FramerError::BufferOverflow { dropped } => {
+                write!(f, "buffer overflow: dropped {} bytes", dropped)
+            }
+        }
+    }
+}
+
+impl Error for FramerError {}
+
+/// Reasons a request to the receiver can fail.
 #[derive(Debug)]
-pub struct PortBuffer {
-    port: TTYPort,
-    buf: Vec<u8>,
+pub enum RequestError {
+    Io(io::Error),
+    /// The receiver rejected the message with UBX-ACK-NAK.
+    Nak {
+        class: u8,
+        id: u8,
+    },
+    /// No acknowledgement or response arrived in time, even after retrying.
+    Timeout {
+        class: u8,
+        id: u8,
+    },
+    /// The response arrived but doesn't carry what was asked for.
+    UnexpectedResponse {
+        class: u8,
+        id: u8,
+    },
+}
+
+impl From<io::Error> for RequestError {
+    fn from(err: io::Error) -> Self {
+        RequestError::Io(err)
+    }
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestError::Io(err) => write!(f, "{}", err),
+            RequestError::Nak { class, id } => {
+                write!(f, "0x{:02x} 0x{:02x}: rejected by the receiver", class, id)
+            }
+            RequestError::Timeout { class, id } => {
+                write!(
+                    f,
+                    "0x{:02x} 0x{:02x}: no response from the receiver",
+                    class, id
+                )
+            }
+            RequestError::UnexpectedResponse { class, id } => {
+                write!(
+                    f,
+                    "0x{:02x} 0x{:02x}: unexpected response from the receiver",
+                    class, id
+                )
+            }
+        }
+    }
 }
 
-impl PortBuffer {
-    pub fn new(port: TTYPort) -> PortBuffer {
-        PortBuffer { port, buf: vec![] }
+impl Error for RequestError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RequestError::Io(err) => Some(err),
+            _ => None,
+        }
     }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PortBufferStats {
+    pub ubx_frames: u64,
+    pub nmea_sentences: u64,
+    /// UBX frames discarded because of a checksum mismatch (strict mode).
+    pub bad_checksum_dropped: u64,
+    /// UBX frames with a checksum mismatch that were decoded anyway (lenient mode).
+    pub bad_checksum_accepted: u64,
+    /// Frames with a valid checksum whose payload couldn't be decoded.
+    pub decode_errors: u64,
+    pub invalid_nmea: u64,
+    pub oversized_frames: u64,
+    /// Bytes discarded while looking for a sync sequence or because of a buffer overflow.
+    pub skipped_bytes: u64,
+}
+
+/// Joins a separate reader and writer into a single port, e.g. a recorded log with `io::sink()`
+/// or the two halves of a network stream.
+#[derive(Debug)]
+pub struct Duplex<R, W> {
+    pub reader: R,
+    pub writer: W,
+}
+
+impl<R, W> Duplex<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Duplex { reader, writer }
+    }
+}
+
+impl<R: Read, W> Read for Duplex<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
 
-    pub fn send(&mut self, msg: Message) {
+impl<R, W: Write> Write for Duplex<R, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[derive(Debug)]
+pub struct PortBuffer<P> {
+    port: P,
+    buf: Vec<u8>,
+    checksum_mode: ChecksumMode,
+    stats: PortBufferStats,
+    overflow: usize,
+    recorder: Option<Recorder>,
+    /// Messages that arrived while waiting for a response, to be returned by `read_msg`.
+    pending: VecDeque<Result<Message, FramerError>>,
+}
+
+impl<P: Read + Write> PortBuffer<P> {
+    pub fn send(&mut self, msg: Message) -> io::Result<()> {
         let bytes = match msg {
             Message::Ublox(ubmsg) => ubmsg.into(),
             Message::Nmea(nmea) => nmea.into_bytes(),
         };
-        self.port.write_all(&bytes).unwrap();
-        self.port.flush().unwrap();
+        self.port.write_all(&bytes)?;
+        self.port.flush()
     }
 
-    pub fn read(&mut self) -> Result<(), String> {
-        let num_bytes = self
-            .port
-            .bytes_to_read()
-            .map_err(|err| format!("{}", err))?;
-        if num_bytes == 0 {
-            return Ok(());
+    /// Sends a message and waits for the receiver to acknowledge it, resending it up to `retries`
+    /// times if nothing arrives within `timeout`. Other messages received in the meantime are
+    /// kept for `read_msg`.
+    pub fn send_and_wait_ack(
+        &mut self,
+        msg: UbloxMsg,
+        timeout: Duration,
+        retries: usize,
+    ) -> Result<(), RequestError> {
+        let (class, id) = msg.class_id();
+        self.request(msg, timeout, retries, |response| match response {
+            UbloxMsg::AckAck(ack) if (ack.class, ack.id) == (class, id) => Some(Ok(())),
+            _ => None,
+        })
+    }
+
+    /// Sends a poll request and returns the receiver's response - the next message with the
+    /// same class and ID. Retries and other messages are handled like in `send_and_wait_ack`.
+    pub fn poll(
+        &mut self,
+        msg: UbloxMsg,
+        timeout: Duration,
+        retries: usize,
+    ) -> Result<UbloxMsg, RequestError> {
+        let (class, id) = msg.class_id();
+        self.request(msg, timeout, retries, |response| {
+            if response.class_id() == (class, id) {
+                Some(Ok(response.clone()))
+            } else {
+                None
+            }
+        })
+    }
+
+    fn request<T, F>(
+        &mut self,
+        msg: UbloxMsg,
+        timeout: Duration,
+        retries: usize,
+        mut response: F,
+    ) -> Result<T, RequestError>
+    where
+        F: FnMut(&UbloxMsg) -> Option<Result<T, RequestError>>,
+    {
+        let (class, id) = msg.class_id();
+        for _ in 0..=retries {
+            self.send(Message::Ublox(msg.clone()))?;
+            let deadline = Instant::now() + timeout;
+            while Instant::now() < deadline {
+                self.read()?;
+                while let Some(result) = self.next_msg() {
+                    if let Ok(Message::Ublox(ref received)) = result {
+                        if let UbloxMsg::AckNak(UbxAckNak {
+                            class: nak_class,
+                            id: nak_id,
+                        }) = *received
+                        {
+                            if (nak_class, nak_id) == (class, id) {
+                                return Err(RequestError::Nak { class, id });
+                            }
+                        }
+                        if let Some(result) = response(received) {
+                            return result;
+                        }
+                    }
+                    self.pending.push_back(result);
+                }
+            }
         }
-        let mut bytes = vec![0; num_bytes as usize];
-        self.port.read_exact(&mut bytes).unwrap();
+        Err(RequestError::Timeout { class, id })
+    }
+
+    /// Reads whatever the port has available into the buffer and returns the number of bytes
+    /// read. Timeouts are not errors - they just yield 0 bytes, as does the end of a file.
+    ///
+    /// If a recorder is set, everything read is also passed to it.
+    pub fn read(&mut self) -> io::Result<usize> {
+        let mut bytes = [0; 4096];
+        match self.port.read(&mut bytes) {
+            Ok(num_bytes) => {
+                if let Some(ref mut recorder) = self.recorder {
+                    if num_bytes > 0 {
+                        recorder.record(&bytes[..num_bytes])?;
+                    }
+                }
+                self.feed(&bytes[..num_bytes]);
+                Ok(num_bytes)
+            }
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    ErrorKind::TimedOut | ErrorKind::WouldBlock | ErrorKind::Interrupted
+                ) =>
+            {
+                Ok(0)
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl<P> PortBuffer<P> {
+    pub fn new(port: P) -> PortBuffer<P> {
+        PortBuffer {
+            port,
+            buf: vec![],
+            checksum_mode: ChecksumMode::Strict,
+            stats: Default::default(),
+            overflow: 0,
+            recorder: None,
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &P {
+        &self.port
+    }
+
+    pub fn get_mut(&mut self) -> &mut P {
+        &mut self.port
+    }
+
+    pub fn into_inner(self) -> P {
+        self.port
+    }
+
+    /// Sets a recorder that gets a copy of every byte read from the port. Returns the previous
+    /// one, if any.
+    pub fn set_recorder(&mut self, recorder: Option<Recorder>) -> Option<Recorder> {
+        mem::replace(&mut self.recorder, recorder)
+    }
+
+    pub fn set_checksum_mode(&mut self, mode: ChecksumMode) {
+        self.checksum_mode = mode;
+    }
+
+    pub fn stats(&self) -> PortBufferStats {
+        self.stats
+    }
+
+    /// The number of bytes waiting in the buffer for the rest of a frame.
+    pub fn buffered_bytes(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Appends bytes obtained by other means than reading the port.
+    pub fn feed(&mut self, bytes: &[u8]) {
         self.buf.extend(bytes);
-        Ok(())
+        if self.buf.len() > MAX_BUFFER_SIZE {
+            let excess = self.buf.len() - MAX_BUFFER_SIZE;
+            self.buf.drain(..excess);
+            self.overflow += excess;
+            self.stats.skipped_bytes += excess as u64;
+        }
     }
 
+    /// Discards everything buffered, e.g. after seeking in the underlying stream.
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.overflow = 0;
+        self.pending.clear();
+    }
+
+    fn is_sync(bytes: &[u8]) -> bool {
+        bytes == [0xb5, 0x62] || (bytes[0] == b'$' && bytes[1].is_ascii_uppercase())
+    }
+
+    /// Discards bytes up to the next UBX or NMEA sync sequence. If there is none, only the last
+    /// byte is kept, as it could be the beginning of one.
     pub fn sync(&mut self) -> bool {
-        let mut i = 0;
         if self.buf.len() < 2 {
             return false;
         }
-        while i < self.buf.len() - 1 {
-            if &self.buf[i..i + 2] == &[0xb5, 0x62]
-                || (self.buf[i] == b'$' && self.buf[i + 1] >= b'A' && self.buf[i + 1] <= b'Z')
-            {
-                let rest = self.buf.split_off(i);
-                self.buf = rest;
-                return true;
+        let skip = match self.buf.windows(2).position(Self::is_sync) {
+            Some(i) => i,
+            None => self.buf.len() - 1,
+        };
+        self.buf.drain(..skip);
+        self.stats.skipped_bytes += skip as u64;
+        self.buf.len() >= 2 && Self::is_sync(&self.buf[0..2])
+    }
+
+    /// Skips the first byte of a bogus sync sequence so that the search for the next one starts
+    /// right after it - a real frame might be hidden in what looked like the body of this one.
+    fn resync(&mut self) {
+        self.buf.drain(..1);
+        self.stats.skipped_bytes += 1;
+    }
+
+    fn nmea_checksum_ok(sentence: &[u8]) -> bool {
+        let body = match sentence.iter().rposition(|c| *c == b'*') {
+            Some(star) if sentence.len() >= star + 3 => &sentence[1..star + 3],
+            _ => return false,
+        };
+        let (data, checksum) = body.split_at(body.len() - 3);
+        let expected = data.iter().fold(0, |acc, c| acc ^ c);
+        std::str::from_utf8(&checksum[1..])
+            .ok()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            == Some(expected)
+    }
+
+    fn read_nmea(&mut self) -> Option<Result<Message, FramerError>> {
+        let end = match self
+            .buf
+            .iter()
+            .take(MAX_NMEA_LENGTH)
+            .position(|c| *c == b'\n')
+        {
+            Some(end) => end,
+            None if self.buf.len() < MAX_NMEA_LENGTH => return None,
+            None => {
+                let bytes = self.buf[..MAX_NMEA_LENGTH].to_vec();
+                self.resync();
+                self.stats.invalid_nmea += 1;
+                return Some(Err(FramerError::InvalidNmea(bytes)));
             }
-            i += 1;
+        };
+        let sentence = &self.buf[..=end];
+        let line = sentence.strip_suffix(b"\r\n").unwrap_or(&sentence[..end]);
+        let valid = line.iter().all(|c| (b' '..=b'~').contains(c)) && Self::nmea_checksum_ok(line);
+        if !valid {
+            let bytes = sentence.to_vec();
+            self.resync();
+            self.stats.invalid_nmea += 1;
+            return Some(Err(FramerError::InvalidNmea(bytes)));
         }
-        false
+        let rest = self.buf.split_off(end + 1);
+        let sentence = mem::replace(&mut self.buf, rest);
+        self.stats.nmea_sentences += 1;
+        // only printable ASCII at this point
+        Some(Ok(Message::Nmea(String::from_utf8(sentence).unwrap())))
     }
 
-    pub fn read_msg(&mut self) -> Option<Message> {
+    fn read_ubx(&mut self) -> Option<Result<Message, FramerError>> {
+        if self.buf.len() < 8 {
+            return None;
+        }
+        let length = u16::from_le_bytes(self.buf[4..6].try_into().unwrap()) as usize;
+        if length > MAX_UBX_PAYLOAD {
+            let (class, id) = (self.buf[2], self.buf[3]);
+            self.resync();
+            self.stats.oversized_frames += 1;
+            return Some(Err(FramerError::OversizedFrame { class, id, length }));
+        }
+        if self.buf.len() < 8 + length {
+            return None;
+        }
+        let raw_msg = match UbloxRawMsg::from_bytes(&self.buf[..8 + length], self.checksum_mode) {
+            Ok(raw_msg) => raw_msg,
+            Err(err) => {
+                // most likely a false sync, so don't throw away the rest of the "frame"
+                self.resync();
+                self.stats.bad_checksum_dropped += 1;
+                return Some(Err(err.into()));
+            }
+        };
+        self.buf.drain(..8 + length);
+        if raw_msg.has_valid_checksum() {
+            self.stats.ubx_frames += 1;
+        } else {
+            self.stats.bad_checksum_accepted += 1;
+        }
+        match UbloxMsg::try_from(raw_msg) {
+            Ok(msg) => Some(Ok(Message::Ublox(msg))),
+            Err(err) => {
+                self.stats.decode_errors += 1;
+                Some(Err(err.into()))
+            }
+        }
+    }
+
+    /// Returns the next complete message in the buffer, if there is one. Anything that can't
+    /// be decoded is skipped and reported as an error; the framer then carries on with the
+    /// rest of the stream, so it's fine to keep calling this after an error.
+    pub fn read_msg(&mut self) -> Option<Result<Message, FramerError>> {
+        self.pending.pop_front().or_else(|| self.next_msg())
+    }
+
+    fn next_msg(&mut self) -> Option<Result<Message, FramerError>> {
+        if self.overflow > 0 {
+            let dropped = mem::replace(&mut self.overflow, 0);
+            return Some(Err(FramerError::BufferOverflow { dropped }));
+        }
         if !self.sync() {
             return None;
         }
-        if self.buf[0] == b'$' && self.buf[1] >= b'A' && self.buf[1] <= b'Z' {
-            let (end, _) = self.buf.iter().enumerate().find(|(_, c)| **c == b'\n')?;
-            let rest = self.buf.split_off(end + 1);
-            let msg = String::from_utf8(mem::replace(&mut self.buf, rest)).unwrap();
-            return Some(Message::Nmea(msg));
+        if self.buf[0] == b'$' {
+            self.read_nmea()
+        } else {
+            self.read_ubx()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{self, Cursor, Write},
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::{
+        Duplex, FramerError, Message, PortBuffer, RequestError, MAX_BUFFER_SIZE, MAX_UBX_PAYLOAD,
+    };
+    use crate::{
+        recorder::Recorder,
+        ublox::{UbloxMsg, UbxAckAck, UbxAckNak, UbxCfgMsg, UbxCfgRate, UbxError},
+    };
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
         }
-        if &self.buf[0..2] == &[0xb5, 0x62] {
-            if self.buf.len() < 8 {
-                return None;
+    }
+
+    fn nmea(body: &str) -> Vec<u8> {
+        let checksum = body.bytes().fold(0, |acc, c| acc ^ c);
+        format!("${}*{:02X}\r\n", body, checksum).into_bytes()
+    }
+
+    fn ubx_frame() -> Vec<u8> {
+        UbloxMsg::CfgMsg(UbxCfgMsg::SetRate {
+            class: 0x02,
+            id: 0x15,
+            rate: 1,
+        })
+        .into()
+    }
+
+    #[test]
+    fn demultiplex_ubx_and_nmea() {
+        let mut input = b"garbage".to_vec();
+        input.extend(nmea("GPGGA,,,,,,0,00,,,M,,M,,"));
+        input.extend(ubx_frame());
+        let mut bad_frame = ubx_frame();
+        bad_frame[8] ^= 0xff;
+        input.extend(bad_frame);
+        input.extend(nmea("GPTXT,01"));
+
+        let mut port = PortBuffer::new(Duplex::new(Cursor::new(input), io::sink()));
+        while port.read().unwrap() > 0 {}
+
+        match port.read_msg() {
+            Some(Ok(Message::Nmea(nmea))) => assert!(nmea.starts_with("$GPGGA")),
+            x => panic!("expected NMEA, got {:?}", x),
+        }
+        match port.read_msg() {
+            Some(Ok(Message::Ublox(UbloxMsg::CfgMsg(UbxCfgMsg::SetRate { rate: 1, .. })))) => {}
+            x => panic!("expected CFG-MSG, got {:?}", x),
+        }
+        match port.read_msg() {
+            Some(Err(FramerError::Ubx(UbxError::BadChecksum { class: 0x06, .. }))) => {}
+            x => panic!("expected a checksum error, got {:?}", x),
+        }
+        match port.read_msg() {
+            Some(Ok(Message::Nmea(sentence))) => assert_eq!(sentence.as_bytes(), nmea("GPTXT,01")),
+            x => panic!("expected NMEA, got {:?}", x),
+        }
+        assert!(port.read_msg().is_none());
+
+        let stats = port.stats();
+        assert_eq!(stats.ubx_frames, 1);
+        assert_eq!(stats.nmea_sentences, 2);
+        assert_eq!(stats.bad_checksum_dropped, 1);
+    }
+
+    #[test]
+    fn recover_from_false_sync() {
+        // a false sync with a plausible length swallowing the beginning of a real frame, then
+        // an oversized length, an unterminated NMEA lookalike and invalid UTF-8
+        let mut input = vec![0xb5, 0x62, 0x01, 0x02, 0x06, 0x00, 0x00];
+        input.extend(ubx_frame());
+        input.extend(&[0xb5, 0x62, 0x01, 0x02, 0xff, 0xff]);
+        input.extend(b"$GP");
+        input.extend(vec![b'x'; 200]);
+        input.extend(b"$GP\xff\xfe\r\n");
+        input.extend(ubx_frame());
+
+        let mut port = PortBuffer::new(io::empty());
+        port.feed(&input);
+        let mut messages = 0;
+        let mut errors = 0;
+        while let Some(result) = port.read_msg() {
+            match result {
+                Ok(Message::Ublox(UbloxMsg::CfgMsg(_))) => messages += 1,
+                Ok(msg) => panic!("unexpected message: {:?}", msg),
+                Err(_) => errors += 1,
             }
-            let length = u16::from_le_bytes(self.buf[4..6].try_into().unwrap()) as usize;
-            if self.buf.len() < 8 + length {
-                return None;
+        }
+        assert_eq!(messages, 2);
+        assert_eq!(errors, 4);
+        let stats = port.stats();
+        assert_eq!(stats.oversized_frames, 1);
+        assert_eq!(stats.invalid_nmea, 2);
+    }
+
+    #[test]
+    fn garbage_never_panics() {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut port = PortBuffer::new(io::empty());
+        for _ in 0..2000 {
+            let mut chunk = vec![];
+            for _ in 0..97 {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                // bias the input towards sync characters to exercise the framing paths
+                chunk.push(match state % 8 {
+                    0 => 0xb5,
+                    1 => 0x62,
+                    2 => b'$',
+                    3 => b'\n',
+                    _ => (state >> 32) as u8,
+                });
             }
-            let rest = self.buf.split_off(8 + length);
-            let msg = mem::replace(&mut self.buf, rest);
-            return Some(Message::Ublox(msg.try_into().unwrap()));
+            port.feed(&chunk);
+            while port.read_msg().is_some() {}
+            assert!(port.buffered_bytes() <= MAX_BUFFER_SIZE);
+        }
+        // flush out whatever partial frame is still pending
+        port.feed(&[0; MAX_UBX_PAYLOAD + 8]);
+        while port.read_msg().is_some() {}
+        port.feed(&ubx_frame());
+        assert!(matches!(port.read_msg(), Some(Ok(Message::Ublox(_)))));
+    }
+
+    #[test]
+    fn record_everything_read() {
+        let mut input = b"noise".to_vec();
+        input.extend(ubx_frame());
+        let log = SharedBuf::default();
+        let mut port = PortBuffer::new(Duplex::new(Cursor::new(input.clone()), io::sink()));
+        port.set_recorder(Some(Recorder::new(log.clone())));
+        while port.read().unwrap() > 0 {}
+        while port.read_msg().is_some() {}
+        assert_eq!(*log.0.lock().unwrap(), input);
+    }
+
+    #[test]
+    fn wait_for_ack() {
+        let mut input = nmea("GPTXT,01");
+        input.extend(Vec::<u8>::from(UbloxMsg::AckAck(UbxAckAck {
+            class: UbxCfgRate::CLASS,
+            id: UbxCfgRate::ID,
+        })));
+        input.extend(Vec::<u8>::from(UbloxMsg::AckNak(UbxAckNak {
+            class: UbxCfgMsg::CLASS,
+            id: UbxCfgMsg::ID,
+        })));
+        let mut port = PortBuffer::new(Duplex::new(Cursor::new(input), io::sink()));
+        let timeout = Duration::from_millis(10);
+
+        let msg = UbloxMsg::CfgMsg(UbxCfgMsg::SetRate {
+            class: 0x02,
+            id: 0x15,
+            rate: 1,
+        });
+        match port.send_and_wait_ack(msg, timeout, 1) {
+            Err(RequestError::Nak {
+                class: 0x06,
+                id: 0x01,
+            }) => {}
+            x => panic!("expected a NAK, got {:?}", x),
+        }
+        // the messages before the NAK are still there
+        match port.read_msg() {
+            Some(Ok(Message::Nmea(_))) => {}
+            x => panic!("expected NMEA, got {:?}", x),
+        }
+        match port.read_msg() {
+            Some(Ok(Message::Ublox(UbloxMsg::AckAck(_)))) => {}
+            x => panic!("expected ACK-ACK, got {:?}", x),
         }
-        None
+        assert!(port.read_msg().is_none());
+
+        match port.poll(
+            UbloxMsg::CfgMsg(UbxCfgMsg::Get {
+                class: 0x02,
+                id: 0x15,
+            }),
+            timeout,
+            2,
+        ) {
+            Err(RequestError::Timeout {
+                class: 0x06,
+                id: 0x01,
+            }) => {}
+            x => panic!("expected a timeout, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn send_to_writer() {
+        let mut port = PortBuffer::new(Duplex::new(io::empty(), vec![]));
+        let msg = UbloxMsg::CfgMsg(UbxCfgMsg::Get {
+            class: 0x02,
+            id: 0x15,
+        });
+        port.send(Message::Ublox(msg.clone())).unwrap();
+        assert_eq!(port.into_inner().writer, Vec::<u8>::from(msg));
     }
 }
@@ -0,0 +1,227 @@
+use std::{error::Error, fmt};
+
+use nalgebra::{Matrix4, Vector3, Vector4};
+
+use super::PositionSolution;
+use crate::{coords::Geodetic, gps_status::GpsStatus};
+
+/// Dilution of precision: how much the satellite geometry scales pseudorange errors into position
+/// and time errors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dop {
+    pub gdop: f64,
+    pub pdop: f64,
+    pub hdop: f64,
+    pub vdop: f64,
+    pub tdop: f64,
+}
+
+impl Dop {
+    /// Computes the DOP from the directions to the satellites in the receiver's east-north-up
+    /// frame. Returns `None` if the geometry doesn't determine the position.
+    pub fn from_directions<I: IntoIterator<Item = Vector3<f64>>>(directions: I) -> Option<Self> {
+        let mut normal = Matrix4::zeros();
+        for direction in directions {
+            let unit = direction.normalize();
+            let h = Vector4::new(-unit.x, -unit.y, -unit.z, 1.0);
+            normal += h * h.transpose();
+        }
+        let q = normal.try_inverse()?;
+        Some(Dop {
+            gdop: q.trace().sqrt(),
+            pdop: (q[(0, 0)] + q[(1, 1)] + q[(2, 2)]).sqrt(),
+            hdop: (q[(0, 0)] + q[(1, 1)]).sqrt(),
+            vdop: q[(2, 2)].sqrt(),
+            tdop: q[(3, 3)].sqrt(),
+        })
+    }
+}
+
+impl PositionSolution {
+    /// The DOP of the satellites used in the solution.
+    pub fn dop(&self) -> Option<Dop> {
+        let receiver = self.geodetic();
+        Dop::from_directions(
+            self.satellites
+                .iter()
+                .map(|sat| receiver.to_enu(self.position + sat.line_of_sight)),
+        )
+    }
+}
+
+/// The satellites above the elevation mask at a given time and their DOP.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DopSample {
+    /// GPS time, in seconds.
+    pub time: f64,
+    pub satellites: Vec<u8>,
+    /// `None` if there are fewer than 4 satellites or they don't determine the position.
+    pub dop: Option<Dop>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ForecastError {
+    /// The step isn't a positive number of seconds.
+    InvalidStep(f64),
+    /// The duration isn't a finite, non-negative number of seconds.
+    InvalidDuration(f64),
+}
+
+impl fmt::Display for ForecastError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ForecastError::InvalidStep(step) => {
+                write!(f, "invalid forecast step: {} s, must be positive", step)
+            }
+            ForecastError::InvalidDuration(duration) => {
+                write!(
+                    f,
+                    "invalid forecast duration: {} s, must not be negative",
+                    duration
+                )
+            }
+        }
+    }
+}
+
+impl Error for ForecastError {}
+
+/// The DOP of the satellites with a complete ephemeris, now.
+pub fn current_dop(gps_status: &GpsStatus, receiver: &Geodetic, elevation_mask: f64) -> DopSample {
+    let time = gps_status.gps_time();
+    let positions = gps_status
+        .complete_satellites()
+        .filter(|(sv_id, _)| healthy(gps_status, *sv_id))
+        .map(|(sv_id, elements)| (sv_id, elements.position(time)));
+    sample(time, receiver, positions, elevation_mask)
+}
+
+/// The DOP from `start` until `start + duration` every `step` seconds, predicted from the
+/// ephemerides and, for satellites without one, the almanac. The ephemerides get less accurate
+/// hours away from their reference time, but still far more accurate than needed for the DOP.
+pub fn forecast_dop(
+    gps_status: &GpsStatus,
+    receiver: &Geodetic,
+    start: f64,
+    duration: f64,
+    step: f64,
+    elevation_mask: f64,
+) -> Result<Vec<DopSample>, ForecastError> {
+    if !(step.is_finite() && step > 0.0) {
+        return Err(ForecastError::InvalidStep(step));
+    }
+    if !(duration.is_finite() && duration >= 0.0) {
+        return Err(ForecastError::InvalidDuration(duration));
+    }
+    let satellites: Vec<_> = gps_status
+        .predicted_satellites()
+        .filter(|(sv_id, _)| healthy(gps_status, *sv_id))
+        .collect();
+    let steps = (duration / step).floor() as usize;
+    Ok((0..=steps)
+        .map(|i| {
+            let time = start + i as f64 * step;
+            let positions = satellites
+                .iter()
+                .map(|(sv_id, elements)| (*sv_id, elements.position(time)));
+            sample(time, receiver, positions, elevation_mask)
+        })
+        .collect())
+}
+
+fn healthy(gps_status: &GpsStatus, sv_id: u8) -> bool {
+    !matches!(
+        gps_status.satellite(sv_id).and_then(|status| status.health()),
+        Some(health) if health != 0
+    )
+}
+
+fn sample<I: Iterator<Item = (u8, Vector3<f64>)>>(
+    time: f64,
+    receiver: &Geodetic,
+    positions: I,
+    elevation_mask: f64,
+) -> DopSample {
+    let mut satellites = vec![];
+    let mut directions = vec![];
+    for (sv_id, position) in positions {
+        if receiver.look_angles(position).1 >= elevation_mask {
+            satellites.push(sv_id);
+            directions.push(receiver.to_enu(position));
+        }
+    }
+    satellites.sort_unstable();
+    let dop = if satellites.len() >= 4 {
+        Dop::from_directions(directions)
+    } else {
+        None
+    };
+    DopSample {
+        time,
+        satellites,
+        dop,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::positioning::spp::test::gps_status;
+
+    #[test]
+    fn dop_of_ideal_geometry() {
+        // one satellite at the zenith and three evenly spread on the horizon
+        let directions = vec![
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.75f64.sqrt(), -0.5, 0.0),
+            Vector3::new(-(0.75f64.sqrt()), -0.5, 0.0),
+        ];
+        let dop = Dop::from_directions(directions.clone()).unwrap();
+        assert!((dop.hdop - (4.0f64 / 3.0).sqrt()).abs() < 1e-9);
+        assert!((dop.vdop - (4.0f64 / 3.0).sqrt()).abs() < 1e-9);
+        assert!((dop.tdop - (1.0f64 / 3.0).sqrt()).abs() < 1e-9);
+        assert!((dop.pdop - (8.0f64 / 3.0).sqrt()).abs() < 1e-9);
+        assert!((dop.gdop - 3.0f64.sqrt()).abs() < 1e-9);
+
+        assert_eq!(Dop::from_directions(directions.into_iter().take(3)), None);
+    }
+
+    #[test]
+    fn dop_of_ephemerides() {
+        let mut status = gps_status();
+        let start = 2100.0 * 604800.0 + 7300.0;
+        status.set_time_correction(start);
+        let receiver = Geodetic::new(20.0, 5.0, 100.0);
+
+        let current = current_dop(&status, &receiver, 0.0);
+        assert!((current.time - start).abs() < 1.0);
+        assert_eq!(current.satellites, vec![1, 2, 3, 4, 5, 6]);
+        assert!(current.dop.unwrap().pdop.is_finite());
+        let masked = current_dop(&status, &receiver, 90.0);
+        assert!(masked.satellites.is_empty());
+        assert_eq!(masked.dop, None);
+
+        let forecast = forecast_dop(&status, &receiver, start, 3600.0, 600.0, 0.0).unwrap();
+        assert_eq!(forecast.len(), 7);
+        assert_eq!(forecast[6].time, start + 3600.0);
+        assert_eq!(forecast[0].satellites, current.satellites);
+        let pdop = |sample: &DopSample| sample.dop.unwrap().pdop;
+        assert!((pdop(&forecast[0]) - pdop(&current)).abs() < 1e-3);
+        let single = forecast_dop(&status, &receiver, start, 0.0, 600.0, 0.0).unwrap();
+        assert_eq!(single.len(), 1);
+
+        for step in [0.0, -600.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                forecast_dop(&status, &receiver, start, 3600.0, step, 0.0),
+                Err(ForecastError::InvalidStep(_))
+            ));
+        }
+        for duration in [-1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                forecast_dop(&status, &receiver, start, duration, 600.0, 0.0),
+                Err(ForecastError::InvalidDuration(_))
+            ));
+        }
+    }
+}
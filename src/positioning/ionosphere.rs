@@ -0,0 +1,68 @@
+//! The broadcast Klobuchar model of the ionospheric delay (IS-GPS-200, 20.3.3.5.2.5).
+
+use std::f64::consts::PI;
+
+use super::SPEED_OF_LIGHT;
+
+/// The ionospheric delay of the L1 signal, in meters, for a receiver at `lat`, `lon` seeing the
+/// satellite at azimuth `az` and elevation `el`, all in degrees, at `gps_tow` seconds of the week.
+/// `alpha` and `beta` are the coefficients from subframe 4, page 18.
+pub fn klobuchar(
+    alpha: &[f64; 4],
+    beta: &[f64; 4],
+    lat: f64,
+    lon: f64,
+    az: f64,
+    el: f64,
+    gps_tow: f64,
+) -> f64 {
+    // the model works in semicircles
+    let el = el / 180.0;
+    let az = az.to_radians();
+
+    // Earth's central angle between the receiver and the ionospheric pierce point
+    let psi = 0.0137 / (el + 0.11) - 0.022;
+    let pierce_lat = (lat / 180.0 + psi * az.cos()).clamp(-0.416, 0.416);
+    let pierce_lon = lon / 180.0 + psi * az.sin() / (pierce_lat * PI).cos();
+    let geomagnetic_lat = pierce_lat + 0.064 * ((pierce_lon - 1.617) * PI).cos();
+
+    let local_time = (4.32e4 * pierce_lon + gps_tow).rem_euclid(86400.0);
+    let polynomial = |coefficients: &[f64; 4]| {
+        coefficients
+            .iter()
+            .rev()
+            .fold(0.0, |sum, coefficient| sum * geomagnetic_lat + coefficient)
+    };
+    let amplitude = polynomial(alpha).max(0.0);
+    let period = polynomial(beta).max(72000.0);
+
+    let phase = 2.0 * PI * (local_time - 50400.0) / period;
+    let slant_factor = 1.0 + 16.0 * (0.53 - el).powi(3);
+    let delay = if phase.abs() < 1.57 {
+        5e-9 + amplitude * (1.0 - phase.powi(2) / 2.0 + phase.powi(4) / 24.0)
+    } else {
+        5e-9
+    };
+    slant_factor * delay * SPEED_OF_LIGHT
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn klobuchar_delay() {
+        let alpha = [3.82e-8, 1.49e-8, -1.79e-7, 0.0];
+        let beta = [1.43e5, 0.0, -3.28e5, 1.13e5];
+
+        // the night time delay is a constant 5 ns scaled by the slant factor
+        let night = klobuchar(&alpha, &beta, 40.0, -100.0, 0.0, 90.0, 24000.0);
+        let slant_factor = 1.0 + 16.0 * (0.53 - 0.5f64).powi(3);
+        assert!((night - slant_factor * 5e-9 * SPEED_OF_LIGHT).abs() < 1e-9);
+
+        let zenith = klobuchar(&alpha, &beta, 40.0, -100.0, 0.0, 90.0, 72000.0);
+        let low = klobuchar(&alpha, &beta, 40.0, -100.0, 180.0, 10.0, 72000.0);
+        assert!(zenith > 5.0 && zenith < 15.0);
+        assert!(low > 2.0 * zenith);
+    }
+}
@@ -0,0 +1,18 @@
+//! Receiver navigation solutions computed from the raw measurements in RXM-RAWX and the broadcast
+//! ephemerides, independently of the receiver's own solution.
+
+pub mod ionosphere;
+pub mod troposphere;
+
+mod dop;
+mod raim;
+mod spp;
+mod velocity;
+
+pub use dop::*;
+pub use raim::*;
+pub use spp::*;
+pub use velocity::*;
+
+/// Speed of light in vacuum as used by GPS, in m/s.
+pub const SPEED_OF_LIGHT: f64 = 299_792_458.0;
@@ -0,0 +1,291 @@
+//! Receiver autonomous integrity monitoring: detects a faulty pseudorange from the consistency of
+//! the redundant measurements in a position solution, excludes it and bounds the position error
+//! that an undetected fault could cause.
+
+use std::fmt;
+
+use nalgebra::{Matrix4, Vector3, Vector4};
+
+use super::{Epoch, PositionSolution, SolutionError, SppSettings};
+use crate::{gps_status::GpsStatus, ublox::UbxRxmRawx};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaimSettings {
+    /// Probability that the test flags a fault-free solution.
+    pub false_alarm_probability: f64,
+    /// Probability that a fault causing an error at the protection level goes undetected.
+    pub missed_detection_probability: f64,
+    /// How many satellites may be excluded from one solution.
+    pub max_exclusions: usize,
+}
+
+impl Default for RaimSettings {
+    fn default() -> Self {
+        Self {
+            false_alarm_probability: 1e-5,
+            missed_detection_probability: 1e-3,
+            max_exclusions: 1,
+        }
+    }
+}
+
+/// The chi-square test of the weighted sum of squared residuals of a solution, and the protection
+/// levels derived from it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FaultTest {
+    pub test_statistic: f64,
+    /// The test statistic of a fault-free solution exceeds it with the false alarm probability.
+    pub threshold: f64,
+    /// Horizontal protection level, in meters.
+    pub hpl: f64,
+    /// Vertical protection level, in meters.
+    pub vpl: f64,
+}
+
+impl FaultTest {
+    pub fn fault_detected(&self) -> bool {
+        self.test_statistic > self.threshold
+    }
+}
+
+/// A satellite left out of a solution because the test with it failed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Exclusion {
+    pub sv_id: u8,
+    /// The test statistic of the solution including the satellite.
+    pub test_statistic: f64,
+    pub threshold: f64,
+    /// The residual of the satellite divided by its standard deviation, the largest in the
+    /// solution.
+    pub normalized_residual: f64,
+}
+
+impl fmt::Display for Exclusion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "excluded G{:02}: test statistic {:.1} over threshold {:.1}, normalized residual {:.1}",
+            self.sv_id, self.test_statistic, self.threshold, self.normalized_residual
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RaimSolution {
+    /// The solution without the excluded satellites.
+    pub solution: PositionSolution,
+    /// `None` with fewer than 5 satellites, when there is no redundancy to test.
+    pub test: Option<FaultTest>,
+    /// In the order they were excluded.
+    pub exclusions: Vec<Exclusion>,
+}
+
+impl RaimSolution {
+    /// Whether a fault remains in the solution after the exclusions.
+    pub fn fault_detected(&self) -> bool {
+        matches!(self.test, Some(test) if test.fault_detected())
+    }
+}
+
+/// Computes the receiver position like [`solve`](super::solve), then tests its residuals for a
+/// fault and excludes the satellite with the largest normalized residual until the test passes,
+/// there are too few satellites left to test or `max_exclusions` are excluded.
+pub fn solve_with_raim(
+    raw: &UbxRxmRawx,
+    gps_status: &GpsStatus,
+    settings: &SppSettings,
+    raim_settings: &RaimSettings,
+) -> Result<RaimSolution, SolutionError> {
+    let mut epoch = Epoch::new(raw, gps_status);
+    let mut solution = epoch.solve(settings)?;
+    let mut exclusions = vec![];
+    while let Some(analysis) = Analysis::new(&solution) {
+        let test = analysis.test(raim_settings);
+        // after an exclusion, at least 5 satellites must be left to test the solution again
+        if !test.fault_detected()
+            || exclusions.len() >= raim_settings.max_exclusions
+            || solution.satellites.len() < 6
+        {
+            return Ok(RaimSolution {
+                solution,
+                test: Some(test),
+                exclusions,
+            });
+        }
+
+        let (worst, normalized_residual) = analysis
+            .normalized_residuals
+            .iter()
+            .enumerate()
+            .map(|(i, residual)| (i, residual.abs()))
+            .fold((0, 0.0), |worst, candidate| {
+                if candidate.1 > worst.1 {
+                    candidate
+                } else {
+                    worst
+                }
+            });
+        let sv_id = solution.satellites[worst].sv_id;
+        epoch.exclude(sv_id);
+        match epoch.solve(settings) {
+            Ok(excluded) => solution = excluded,
+            // keep the faulty solution rather than none
+            Err(_) => {
+                return Ok(RaimSolution {
+                    solution,
+                    test: Some(test),
+                    exclusions,
+                })
+            }
+        }
+        exclusions.push(Exclusion {
+            sv_id,
+            test_statistic: test.test_statistic,
+            threshold: test.threshold,
+            normalized_residual,
+        });
+    }
+    Ok(RaimSolution {
+        solution,
+        test: None,
+        exclusions,
+    })
+}
+
+/// The redundancy of each satellite in a solution.
+struct Analysis {
+    /// Weighted sum of squared residuals.
+    sse: f64,
+    degrees_of_freedom: usize,
+    normalized_residuals: Vec<f64>,
+    /// Horizontal and vertical position error per unit of the test statistic's square root, caused
+    /// by a bias in each satellite.
+    slopes: Vec<(f64, f64)>,
+}
+
+impl Analysis {
+    fn new(solution: &PositionSolution) -> Option<Self> {
+        let satellites = &solution.satellites;
+        if satellites.len() < 5 {
+            return None;
+        }
+        let design = |los: Vector3<f64>| Vector4::new(-los.x, -los.y, -los.z, 1.0);
+        let mut normal = Matrix4::zeros();
+        for sat in satellites {
+            let h = design(sat.line_of_sight);
+            normal += h * h.transpose() * sat.weight;
+        }
+        let covariance = normal.try_inverse()?;
+
+        let receiver = solution.geodetic();
+        let mut normalized_residuals = vec![];
+        let mut slopes = vec![];
+        for sat in satellites {
+            let h = design(sat.line_of_sight);
+            // the change of the solution per meter of bias in this pseudorange
+            let gain = covariance * h * sat.weight;
+            // the part of a bias that shows in the residuals rather than the solution
+            let redundancy = (1.0 - h.dot(&gain)).max(0.0);
+            let residual_variance = redundancy / sat.weight;
+            let ned = receiver.ecef_to_ned(gain.xyz());
+            if residual_variance > 0.0 {
+                normalized_residuals.push(sat.residual / residual_variance.sqrt());
+                let scale = (sat.weight * redundancy).sqrt();
+                slopes.push((ned.xy().norm() / scale, ned.z.abs() / scale));
+            } else {
+                // a bias in this satellite goes entirely into the solution, undetectable
+                normalized_residuals.push(0.0);
+                slopes.push((f64::INFINITY, f64::INFINITY));
+            }
+        }
+
+        Some(Analysis {
+            sse: satellites
+                .iter()
+                .map(|sat| sat.weight * sat.residual * sat.residual)
+                .sum(),
+            degrees_of_freedom: satellites.len() - 4,
+            normalized_residuals,
+            slopes,
+        })
+    }
+
+    fn test(&self, settings: &RaimSettings) -> FaultTest {
+        let threshold =
+            chi_square_quantile(self.degrees_of_freedom, settings.false_alarm_probability);
+        // the smallest bias the test detects with the missed detection probability, in units of
+        // the test statistic's square root
+        let detectable = threshold.sqrt() + normal_quantile(settings.missed_detection_probability);
+        let (horizontal, vertical) = self.slopes.iter().fold((0.0f64, 0.0f64), |(h, v), slope| {
+            (h.max(slope.0), v.max(slope.1))
+        });
+        FaultTest {
+            test_statistic: self.sse,
+            threshold,
+            hpl: horizontal * detectable,
+            vpl: vertical * detectable,
+        }
+    }
+}
+
+/// The value exceeded with probability `p` by a chi-square variable with `degrees_of_freedom`,
+/// from the Wilson-Hilferty approximation.
+fn chi_square_quantile(degrees_of_freedom: usize, p: f64) -> f64 {
+    let k = degrees_of_freedom as f64;
+    let spread = 2.0 / (9.0 * k);
+    k * (1.0 - spread + normal_quantile(p) * spread.sqrt()).powi(3)
+}
+
+/// The value exceeded with probability `p`, at most 0.5, by a standard normal variable, within
+/// 4.5e-4 (Abramowitz and Stegun 26.2.23).
+fn normal_quantile(p: f64) -> f64 {
+    let t = (-2.0 * p.ln()).sqrt();
+    t - (2.515517 + 0.802853 * t + 0.010328 * t * t)
+        / (1.0 + 1.432788 * t + 0.189269 * t * t + 0.001308 * t * t * t)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        coords::Geodetic,
+        positioning::spp::test::{gps_status, measurements},
+        ublox::UbxRxmRawxRecvStatus,
+    };
+
+    #[test]
+    fn excludes_faulty_satellite() {
+        let status = gps_status();
+        let receiver = Geodetic::new(20.0, 5.0, 100.0);
+        let mut raw = UbxRxmRawx {
+            rcv_tow: 7300.0,
+            week: 2100,
+            leap_sec: 18,
+            recv_status: UbxRxmRawxRecvStatus::empty(),
+            measurements: measurements(&status, &receiver, Vector3::zeros()),
+        };
+        let settings = SppSettings {
+            elevation_mask: 0.0,
+            ..SppSettings::default()
+        };
+        let raim_settings = RaimSettings::default();
+
+        let clean = solve_with_raim(&raw, &status, &settings, &raim_settings).unwrap();
+        let test = clean.test.unwrap();
+        assert!(!clean.fault_detected());
+        assert!(clean.exclusions.is_empty());
+        assert!(test.hpl.is_finite() && test.hpl > 0.0);
+        assert!(test.vpl.is_finite() && test.vpl > 0.0);
+
+        let faulty = raw.measurements[2].sv_id;
+        raw.measurements[2].pseudorange += 100.0;
+        let excluded = solve_with_raim(&raw, &status, &settings, &raim_settings).unwrap();
+        assert_eq!(excluded.exclusions.len(), 1);
+        let exclusion = excluded.exclusions[0];
+        assert_eq!(exclusion.sv_id, faulty);
+        assert!(exclusion.test_statistic > exclusion.threshold);
+        assert!(!excluded.fault_detected());
+        assert_eq!(excluded.solution.satellites.len(), 5);
+        assert!((excluded.solution.position - receiver.to_ecef()).norm() < 1e-3);
+    }
+}
@@ -0,0 +1,531 @@
+use std::{error::Error, fmt};
+
+use nalgebra::{Matrix4, Rotation3, Vector3, Vector4};
+
+use super::{
+    ionosphere, solve_velocity, troposphere, RangeRate, VelocitySolution, L1_WAVELENGTH,
+    SPEED_OF_LIGHT,
+};
+use crate::{
+    coords::Geodetic,
+    gps_status::{GpsStatus, SatelliteOrbitalElements, SatelliteState, OMEGA_E},
+    ublox::{
+        GnssId, GpsIonosphere, UbxRxmRawx, UbxRxmRawxMeasurement, UbxRxmRawxMeasurementTrkStatus,
+    },
+};
+
+/// Pseudorange error of a satellite at the zenith received at `REFERENCE_CNO`, in meters, on top
+/// of the error estimated by the receiver.
+const ZENITH_SIGMA: f64 = 0.5;
+/// Signal strength in dBHz at which a measurement gets the nominal weight.
+const REFERENCE_CNO: f64 = 45.0;
+/// Position and clock update, in meters, below which the solution is considered converged.
+const CONVERGENCE_LIMIT: f64 = 1e-4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SppSettings {
+    /// Satellites below this elevation, in degrees, are left out of the solution.
+    pub elevation_mask: f64,
+    pub max_iterations: usize,
+}
+
+impl Default for SppSettings {
+    fn default() -> Self {
+        Self {
+            elevation_mask: 10.0,
+            max_iterations: 10,
+        }
+    }
+}
+
+/// A satellite used in a solution.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolutionSatellite {
+    pub sv_id: u8,
+    /// Degrees.
+    pub azimuth: f64,
+    /// Degrees.
+    pub elevation: f64,
+    /// Unit vector from the receiver to the satellite, in ECEF.
+    pub line_of_sight: Vector3<f64>,
+    /// Measured minus modelled pseudorange, in meters.
+    pub residual: f64,
+    /// Inverse of the pseudorange variance, in 1/m^2.
+    pub weight: f64,
+}
+
+/// A single point position: weighted least squares fit of the receiver position and clock to the
+/// pseudoranges of one measurement epoch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionSolution {
+    /// GPS time of the measurements in seconds, as given by the receiver clock.
+    pub time: f64,
+    /// ECEF, in meters.
+    pub position: Vector3<f64>,
+    /// Receiver clock offset from GPS time, in meters.
+    pub clock_bias: f64,
+    pub satellites: Vec<SolutionSatellite>,
+    /// From the Doppler measurements of the same satellites.
+    pub velocity: Option<VelocitySolution>,
+}
+
+impl PositionSolution {
+    pub fn geodetic(&self) -> Geodetic {
+        Geodetic::from_ecef(self.position)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolutionError {
+    /// Fewer than 4 GPS satellites with a valid pseudorange, ephemeris and clock above the
+    /// elevation mask.
+    NotEnoughSatellites {
+        available: usize,
+    },
+    /// The satellite geometry doesn't determine the position.
+    Singular,
+    NotConverged,
+}
+
+impl fmt::Display for SolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolutionError::NotEnoughSatellites { available } => {
+                write!(f, "not enough satellites: {} usable, 4 needed", available)
+            }
+            SolutionError::Singular => write!(f, "degenerate satellite geometry"),
+            SolutionError::NotConverged => write!(f, "the solution didn't converge"),
+        }
+    }
+}
+
+impl Error for SolutionError {}
+
+struct Observation<'a> {
+    measurement: &'a UbxRxmRawxMeasurement,
+    elements: &'a SatelliteOrbitalElements,
+}
+
+/// The measurements of one epoch with everything needed to model them.
+pub(super) struct Epoch<'a> {
+    time: f64,
+    observations: Vec<Observation<'a>>,
+    ionosphere: Option<&'a GpsIonosphere>,
+}
+
+/// Computes the receiver position from the GPS pseudoranges of a RXM-RAWX message, using the
+/// ephemerides, satellite health and ionosphere known to `gps_status`.
+pub fn solve(
+    raw: &UbxRxmRawx,
+    gps_status: &GpsStatus,
+    settings: &SppSettings,
+) -> Result<PositionSolution, SolutionError> {
+    Epoch::new(raw, gps_status).solve(settings)
+}
+
+impl<'a> Epoch<'a> {
+    /// The GPS measurements with a valid pseudorange of healthy satellites with an ephemeris and
+    /// clock.
+    pub(super) fn new(raw: &'a UbxRxmRawx, gps_status: &'a GpsStatus) -> Self {
+        let observations = raw
+            .measurements
+            .iter()
+            .filter(|measurement| {
+                measurement.gnss_id == GnssId::Gps
+                    && measurement
+                        .trk_status
+                        .contains(UbxRxmRawxMeasurementTrkStatus::PR_VALID)
+            })
+            .filter_map(|measurement| {
+                let status = gps_status.satellite(measurement.sv_id)?;
+                if matches!(status.health(), Some(health) if health != 0) {
+                    return None;
+                }
+                let elements = status
+                    .orbital_elements()
+                    .filter(|elements| elements.clock().is_some())?;
+                Some(Observation {
+                    measurement,
+                    elements,
+                })
+            })
+            .collect();
+        Epoch {
+            time: raw.week as f64 * 604800.0 + raw.rcv_tow,
+            observations,
+            ionosphere: gps_status.ionosphere(),
+        }
+    }
+
+    /// Leaves the measurement of a satellite out of the solution.
+    pub(super) fn exclude(&mut self, sv_id: u8) {
+        self.observations
+            .retain(|observation| observation.measurement.sv_id != sv_id);
+    }
+
+    pub(super) fn solve(&self, settings: &SppSettings) -> Result<PositionSolution, SolutionError> {
+        let mut position = Vector3::zeros();
+        let mut clock_bias = 0.0;
+        for _ in 0..settings.max_iterations {
+            let satellites = self.model(position, clock_bias, settings);
+            if satellites.len() < 4 {
+                return Err(SolutionError::NotEnoughSatellites {
+                    available: satellites.len(),
+                });
+            }
+
+            let mut normal = Matrix4::zeros();
+            let mut rhs = Vector4::zeros();
+            for sat in &satellites {
+                let h = Vector4::new(
+                    -sat.line_of_sight.x,
+                    -sat.line_of_sight.y,
+                    -sat.line_of_sight.z,
+                    1.0,
+                );
+                normal += h * h.transpose() * sat.weight;
+                rhs += h * (sat.weight * sat.residual);
+            }
+            let update = normal.try_inverse().ok_or(SolutionError::Singular)? * rhs;
+            position += update.xyz();
+            clock_bias += update.w;
+
+            if update.norm() < CONVERGENCE_LIMIT {
+                let satellites = self.model(position, clock_bias, settings);
+                let velocity = solve_velocity(&self.range_rates(position, &satellites)).ok();
+                return Ok(PositionSolution {
+                    time: self.time,
+                    position,
+                    clock_bias,
+                    satellites,
+                    velocity,
+                });
+            }
+        }
+        Err(SolutionError::NotConverged)
+    }
+}
+
+impl Epoch<'_> {
+    /// The satellite position and velocity at signal transmission, in the ECEF frame at reception,
+    /// and its clock.
+    fn satellite_state(
+        &self,
+        observation: &Observation,
+        receiver: Vector3<f64>,
+    ) -> Option<SatelliteState> {
+        let elements = observation.elements;
+        let transmit_time = self.time - observation.measurement.pseudorange / SPEED_OF_LIGHT;
+        let transmit_time = transmit_time - elements.clock_bias(transmit_time)?;
+        let state = elements.state(transmit_time);
+        let rotation = earth_rotation(state.position, receiver);
+        Some(SatelliteState {
+            position: rotation * state.position,
+            velocity: rotation * state.velocity,
+            ..state
+        })
+    }
+
+    /// Linearizes the pseudoranges around a receiver position and clock bias, leaving out
+    /// satellites below the elevation mask.
+    fn model(
+        &self,
+        receiver: Vector3<f64>,
+        clock_bias: f64,
+        settings: &SppSettings,
+    ) -> Vec<SolutionSatellite> {
+        // the first iterations start from the center of the Earth, where elevations and
+        // atmospheric delays make no sense
+        let receiver_geodetic = Some(receiver)
+            .filter(|receiver| receiver.norm() > 6e6)
+            .map(Geodetic::from_ecef);
+
+        self.observations
+            .iter()
+            .filter_map(|observation| {
+                let pseudorange = observation.measurement.pseudorange;
+                let state = self.satellite_state(observation, receiver)?;
+                let satellite = state.position;
+
+                let (azimuth, elevation, atmospheric_delay) = match receiver_geodetic {
+                    Some(geodetic) => {
+                        let (azimuth, elevation) = geodetic.look_angles(satellite);
+                        let delay = self.atmospheric_delay(&geodetic, azimuth, elevation);
+                        (azimuth, elevation, delay)
+                    }
+                    None => (0.0, 90.0, 0.0),
+                };
+                if elevation < settings.elevation_mask {
+                    return None;
+                }
+
+                let range = (satellite - receiver).norm();
+                let corrected =
+                    pseudorange + SPEED_OF_LIGHT * state.clock_bias? - atmospheric_delay;
+                Some(SolutionSatellite {
+                    sv_id: observation.measurement.sv_id,
+                    azimuth,
+                    elevation,
+                    line_of_sight: (satellite - receiver) / range,
+                    residual: corrected - range - clock_bias,
+                    weight: 1.0 / variance(observation.measurement, elevation),
+                })
+            })
+            .collect()
+    }
+
+    /// The Doppler measurements of the satellites used in a solution at `receiver`.
+    fn range_rates(
+        &self,
+        receiver: Vector3<f64>,
+        satellites: &[SolutionSatellite],
+    ) -> Vec<RangeRate> {
+        satellites
+            .iter()
+            .filter_map(|sat| {
+                let observation = self
+                    .observations
+                    .iter()
+                    .find(|observation| observation.measurement.sv_id == sat.sv_id)?;
+                let state = self.satellite_state(observation, receiver)?;
+                let measurement = observation.measurement;
+                let sin_elevation = sat.elevation.to_radians().sin().max(0.1);
+                Some(RangeRate {
+                    sv_id: sat.sv_id,
+                    line_of_sight: sat.line_of_sight,
+                    satellite_velocity: state.velocity,
+                    satellite_clock_drift: state.clock_drift?,
+                    // the Doppler shift is positive when the satellite approaches
+                    range_rate: -L1_WAVELENGTH * measurement.doppler as f64,
+                    variance: (L1_WAVELENGTH * measurement.doppler_stdev as f64 / sin_elevation)
+                        .powi(2),
+                })
+            })
+            .collect()
+    }
+
+    /// The ionospheric delay from the broadcast Klobuchar model, if it is known yet, plus the
+    /// tropospheric delay, in meters.
+    fn atmospheric_delay(&self, receiver: &Geodetic, azimuth: f64, elevation: f64) -> f64 {
+        let ionospheric_delay = self.ionosphere.map_or(0.0, |iono| {
+            ionosphere::klobuchar(
+                &iono.alpha,
+                &iono.beta,
+                receiver.lat,
+                receiver.lon,
+                azimuth,
+                elevation,
+                self.time % 604800.0,
+            )
+        });
+        ionospheric_delay + troposphere::delay(receiver, elevation, self.time)
+    }
+}
+
+/// The rotation from the ECEF frame at signal transmission into the one at reception, which has
+/// turned with the Earth while the signal travelled to the receiver.
+fn earth_rotation(satellite: Vector3<f64>, receiver: Vector3<f64>) -> Rotation3<f64> {
+    let travel_time = (satellite - receiver).norm() / SPEED_OF_LIGHT;
+    Rotation3::from_axis_angle(&Vector3::z_axis(), -OMEGA_E * travel_time)
+}
+
+/// The pseudorange variance in m^2: the receiver's estimate, plus an error growing at low
+/// elevations and weak signals.
+fn variance(measurement: &UbxRxmRawxMeasurement, elevation: f64) -> f64 {
+    let sin_elevation = elevation.to_radians().sin().max(0.1);
+    let cno_factor = 10f64.powf((REFERENCE_CNO - measurement.cno as f64) / 10.0);
+    let stdev = measurement.pseudorange_stdev as f64;
+    stdev * stdev + (ZENITH_SIGMA / sin_elevation).powi(2) * cno_factor
+}
+
+#[cfg(test)]
+pub(super) mod test {
+    use super::*;
+    use crate::ublox::{GpsAlmanacPage, GpsSubframe, GpsUtc, UbxRxmRawxRecvStatus};
+
+    const WEEK: u16 = 2100;
+    const TOW: f64 = 7300.0;
+    /// Receiver clock bias, in meters.
+    const CLOCK_BIAS: f64 = 1000.0;
+    /// Receiver clock drift, in m/s.
+    const CLOCK_DRIFT: f64 = 0.5;
+
+    const IONOSPHERE: GpsIonosphere = GpsIonosphere {
+        alpha: [3.82e-8, 1.49e-8, -1.79e-7, 0.0],
+        beta: [1.43e5, 0.0, -3.28e5, 1.13e5],
+    };
+
+    /// A status with the ionosphere and ephemerides for six satellites spread over two orbital
+    /// planes.
+    pub(in crate::positioning) fn gps_status() -> GpsStatus {
+        let mut status = GpsStatus::new();
+        status.consume_subframe(
+            1,
+            GpsSubframe::Subframe4 {
+                data_id: 1,
+                page: GpsAlmanacPage::IonoUtc {
+                    ionosphere: IONOSPHERE,
+                    utc: GpsUtc {
+                        a0: 0.0,
+                        a1: 0.0,
+                        tot: 0,
+                        wnt: 0,
+                        delta_t_ls: 18,
+                        wn_lsf: 0,
+                        dn: 0,
+                        delta_t_lsf: 18,
+                    },
+                },
+            },
+        );
+        for sv_id in 1..=6u8 {
+            let plane = (sv_id % 2) as f64;
+            status.consume_subframe(
+                sv_id,
+                GpsSubframe::Subframe1 {
+                    week_number: WEEK,
+                    ura_index: 0,
+                    sv_health: 0,
+                    tgd: 0.0,
+                    iodc: 1,
+                    toc: 7200,
+                    af2: 0.0,
+                    af1: 0.0,
+                    af0: sv_id as f64 * 1e-5,
+                },
+            );
+            status.consume_subframe(
+                sv_id,
+                GpsSubframe::Subframe2 {
+                    aodo: 0,
+                    iode: 1,
+                    c_rs: 0.0,
+                    delta_n: 0.0,
+                    m0: 0.1 * sv_id as f64 - 0.35,
+                    c_uc: 0.0,
+                    e: 0.01,
+                    sqrt_a: 5153.6,
+                    c_us: 0.0,
+                    t_oe: 7200,
+                },
+            );
+            status.consume_subframe(
+                sv_id,
+                GpsSubframe::Subframe3 {
+                    iode: 1,
+                    c_ic: 0.0,
+                    omega0: 0.1 + 0.2 * plane,
+                    c_is: 0.0,
+                    i0: 0.3,
+                    c_rc: 0.0,
+                    omega_small: 0.0,
+                    omega_dot: 0.0,
+                    i_dot: 0.0,
+                },
+            );
+        }
+        status
+    }
+
+    /// Pseudoranges and Doppler shifts that a receiver at `receiver` moving at `velocity` (ECEF)
+    /// would measure.
+    pub(in crate::positioning) fn measurements(
+        status: &GpsStatus,
+        receiver: &Geodetic,
+        velocity: Vector3<f64>,
+    ) -> Vec<UbxRxmRawxMeasurement> {
+        let time = WEEK as f64 * 604800.0 + TOW;
+        let position = receiver.to_ecef();
+        status
+            .complete_satellites()
+            .map(|(sv_id, elements)| {
+                let mut pseudorange = 2e7;
+                let mut range_rate = 0.0;
+                for _ in 0..5 {
+                    let transmit_time = time - pseudorange / SPEED_OF_LIGHT;
+                    let transmit_time = transmit_time - elements.clock_bias(transmit_time).unwrap();
+                    let state = elements.state(transmit_time);
+                    let rotation = earth_rotation(state.position, position);
+                    let satellite = rotation * state.position;
+                    let line_of_sight = (satellite - position).normalize();
+                    range_rate = line_of_sight.dot(&(rotation * state.velocity - velocity))
+                        + CLOCK_DRIFT
+                        - SPEED_OF_LIGHT * state.clock_drift.unwrap();
+                    let (azimuth, elevation) = receiver.look_angles(satellite);
+                    let ionospheric_delay = ionosphere::klobuchar(
+                        &IONOSPHERE.alpha,
+                        &IONOSPHERE.beta,
+                        receiver.lat,
+                        receiver.lon,
+                        azimuth,
+                        elevation,
+                        TOW,
+                    );
+                    pseudorange = (satellite - position).norm() + CLOCK_BIAS
+                        - SPEED_OF_LIGHT * state.clock_bias.unwrap()
+                        + ionospheric_delay
+                        + troposphere::delay(receiver, elevation, time);
+                }
+                UbxRxmRawxMeasurement {
+                    pseudorange,
+                    carrier_phase: 0.0,
+                    doppler: (-range_rate / L1_WAVELENGTH) as f32,
+                    gnss_id: GnssId::Gps,
+                    sv_id,
+                    freq_id: 0,
+                    locktime: 0,
+                    cno: 45,
+                    pseudorange_stdev: 1.0,
+                    carrier_phase_stdev: None,
+                    doppler_stdev: 0.1,
+                    trk_status: UbxRxmRawxMeasurementTrkStatus::PR_VALID,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn single_point_position() {
+        let status = gps_status();
+        let receiver = Geodetic::new(20.0, 5.0, 100.0);
+        // 10 m/s north and 5 m/s east
+        let (sin_lat, cos_lat) = 20f64.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = 5f64.to_radians().sin_cos();
+        let north = Vector3::new(-sin_lat * cos_lon, -sin_lat * sin_lon, cos_lat);
+        let east = Vector3::new(-sin_lon, cos_lon, 0.0);
+        let velocity = north * 10.0 + east * 5.0;
+        let raw = UbxRxmRawx {
+            rcv_tow: TOW,
+            week: WEEK,
+            leap_sec: 18,
+            recv_status: UbxRxmRawxRecvStatus::empty(),
+            measurements: measurements(&status, &receiver, velocity),
+        };
+        let settings = SppSettings {
+            elevation_mask: 0.0,
+            ..SppSettings::default()
+        };
+
+        let solution = solve(&raw, &status, &settings).unwrap();
+        assert_eq!(solution.satellites.len(), 6);
+        assert!((solution.position - receiver.to_ecef()).norm() < 1e-3);
+        assert!((solution.clock_bias - CLOCK_BIAS).abs() < 1e-3);
+        assert!(solution
+            .satellites
+            .iter()
+            .all(|sat| sat.residual.abs() < 1e-3));
+
+        let velocity_solution = solution.velocity.as_ref().unwrap();
+        assert_eq!(velocity_solution.satellites.len(), 6);
+        let ned = velocity_solution.ned(&solution.geodetic());
+        assert!((ned - Vector3::new(10.0, 5.0, 0.0)).norm() < 1e-3);
+        assert!((velocity_solution.clock_drift - CLOCK_DRIFT).abs() < 1e-3);
+
+        let mut raw = raw;
+        raw.measurements.truncate(3);
+        assert_eq!(
+            solve(&raw, &status, &settings),
+            Err(SolutionError::NotEnoughSatellites { available: 3 })
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Tropospheric delay: the UNB3 model of the standard atmosphere, Saastamoinen zenith delays and the
+//! Niell mapping functions.
+
+use std::f64::consts::PI;
+
+use crate::coords::Geodetic;
+
+/// Latitudes of the rows of the tables below, in degrees.
+const LATITUDES: [f64; 5] = [15.0, 30.0, 45.0, 60.0, 75.0];
+
+/// UNB3 averages of pressure (hPa), temperature (K), water vapour pressure (hPa), temperature
+/// lapse rate (K/m) and water vapour lapse rate.
+const UNB3_AVERAGE: [[f64; 5]; 5] = [
+    [1013.25, 299.65, 26.31, 6.30e-3, 2.77],
+    [1017.25, 294.15, 21.79, 6.05e-3, 3.15],
+    [1015.75, 283.15, 11.66, 5.58e-3, 2.57],
+    [1011.75, 272.15, 6.78, 5.39e-3, 1.81],
+    [1013.00, 263.65, 4.11, 4.53e-3, 1.55],
+];
+
+/// Seasonal amplitudes of the UNB3 parameters.
+const UNB3_AMPLITUDE: [[f64; 5]; 5] = [
+    [0.00, 0.00, 0.00, 0.00e-3, 0.00],
+    [-3.75, 7.00, 8.85, 0.25e-3, 0.33],
+    [-2.25, 11.00, 7.24, 0.32e-3, 0.46],
+    [-1.75, 15.00, 5.36, 0.81e-3, 0.74],
+    [-0.50, 14.50, 3.39, 0.62e-3, 0.30],
+];
+
+/// Niell hydrostatic mapping coefficients a, b, c: averages and seasonal amplitudes.
+const NIELL_HYDROSTATIC_AVERAGE: [[f64; 3]; 5] = [
+    [1.2769934e-3, 2.9153695e-3, 62.610505e-3],
+    [1.2683230e-3, 2.9152299e-3, 62.837393e-3],
+    [1.2465397e-3, 2.9288445e-3, 63.721774e-3],
+    [1.2196049e-3, 2.9022565e-3, 63.824265e-3],
+    [1.2045996e-3, 2.9024912e-3, 64.258455e-3],
+];
+
+const NIELL_HYDROSTATIC_AMPLITUDE: [[f64; 3]; 5] = [
+    [0.0, 0.0, 0.0],
+    [1.2709626e-5, 2.1414979e-5, 9.0128400e-5],
+    [2.6523662e-5, 3.0160779e-5, 4.3497037e-5],
+    [3.4000452e-5, 7.2562722e-5, 84.795348e-5],
+    [4.1202191e-5, 11.723375e-5, 170.37206e-5],
+];
+
+/// Niell height correction coefficients.
+const NIELL_HEIGHT: [f64; 3] = [2.53e-5, 5.49e-3, 1.14e-3];
+
+/// Niell wet mapping coefficients a, b, c.
+const NIELL_WET: [[f64; 3]; 5] = [
+    [5.8021897e-4, 1.4275268e-3, 4.3472961e-2],
+    [5.6794847e-4, 1.5138625e-3, 4.6729510e-2],
+    [5.8118019e-4, 1.4572752e-3, 4.3908931e-2],
+    [5.9727542e-4, 1.5007428e-3, 4.4626982e-2],
+    [6.1641693e-4, 1.7599082e-3, 5.4736038e-2],
+];
+
+/// Heights, in meters, above which the models are evaluated as if at this height. Nearly all of the
+/// atmosphere is below it, and the UNB3 lapse rates break down above the stratosphere.
+const MAX_HEIGHT: f64 = 40_000.0;
+
+/// Standard gravity, in m/s^2.
+const G: f64 = 9.80665;
+/// Specific gas constant of dry air, in J/(kg K).
+const R_D: f64 = 287.054;
+
+/// Weather at the receiver.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Meteo {
+    /// hPa.
+    pub pressure: f64,
+    /// K.
+    pub temperature: f64,
+    /// Partial pressure of water vapour, in hPa.
+    pub water_vapour_pressure: f64,
+}
+
+impl Meteo {
+    /// The UNB3 average weather at a place and day of the year.
+    pub fn unb3(receiver: &Geodetic, day_of_year: f64) -> Self {
+        let [pressure, temperature, water_vapour_pressure, beta, lambda] =
+            seasonal(&UNB3_AVERAGE, &UNB3_AMPLITUDE, receiver.lat, day_of_year);
+        let height = receiver.height.clamp(0.0, MAX_HEIGHT);
+        let lapse = 1.0 - beta * height / temperature;
+        let exponent = G / (R_D * beta);
+        Meteo {
+            pressure: pressure * lapse.powf(exponent),
+            temperature: temperature - beta * height,
+            water_vapour_pressure: water_vapour_pressure * lapse.powf((lambda + 1.0) * exponent),
+        }
+    }
+
+    /// Saastamoinen hydrostatic and wet zenith delays at the receiver, in meters.
+    pub fn zenith_delays(&self, receiver: &Geodetic) -> (f64, f64) {
+        let height = receiver.height.min(MAX_HEIGHT);
+        let gravity_factor =
+            1.0 - 0.00266 * (2.0 * receiver.lat.to_radians()).cos() - 0.00028e-3 * height;
+        let hydrostatic = 0.0022768 * self.pressure / gravity_factor;
+        let wet = 0.002277 * (1255.0 / self.temperature + 0.05) * self.water_vapour_pressure;
+        (hydrostatic, wet)
+    }
+}
+
+/// The Niell hydrostatic and wet mapping functions: ratios of the slant delay at `elevation`
+/// degrees to the zenith delay.
+pub fn niell_mapping(receiver: &Geodetic, elevation: f64, day_of_year: f64) -> (f64, f64) {
+    let sin_elevation = elevation.max(1.0).to_radians().sin();
+    let [a, b, c] = seasonal(
+        &NIELL_HYDROSTATIC_AVERAGE,
+        &NIELL_HYDROSTATIC_AMPLITUDE,
+        receiver.lat,
+        day_of_year,
+    );
+    let height = receiver.height.min(MAX_HEIGHT);
+    let height_correction =
+        (1.0 / sin_elevation - marini(sin_elevation, NIELL_HEIGHT)) * height / 1000.0;
+    let hydrostatic = marini(sin_elevation, [a, b, c]) + height_correction;
+    let wet = marini(sin_elevation, interpolate(&NIELL_WET, receiver.lat));
+    (hydrostatic, wet)
+}
+
+/// The slant tropospheric delay in meters of a signal received at `elevation` degrees, at the GPS
+/// time `gps_time`.
+pub fn delay(receiver: &Geodetic, elevation: f64, gps_time: f64) -> f64 {
+    let day_of_year = day_of_year(gps_time);
+    let (zenith_hydrostatic, zenith_wet) =
+        Meteo::unb3(receiver, day_of_year).zenith_delays(receiver);
+    let (hydrostatic, wet) = niell_mapping(receiver, elevation, day_of_year);
+    zenith_hydrostatic * hydrostatic + zenith_wet * wet
+}
+
+/// Marini's continued fraction, normalized to 1 at the zenith.
+fn marini(sin_elevation: f64, [a, b, c]: [f64; 3]) -> f64 {
+    (1.0 + a / (1.0 + b / (1.0 + c)))
+        / (sin_elevation + a / (sin_elevation + b / (sin_elevation + c)))
+}
+
+/// Interpolates a table row by the latitude, symmetric between the hemispheres.
+fn interpolate<const N: usize>(table: &[[f64; N]; 5], lat: f64) -> [f64; N] {
+    let lat = lat.abs();
+    if lat <= LATITUDES[0] {
+        return table[0];
+    }
+    if lat >= LATITUDES[4] {
+        return table[4];
+    }
+    let i = ((lat - LATITUDES[0]) / 15.0).floor() as usize;
+    let fraction = (lat - LATITUDES[i]) / 15.0;
+    let mut row = [0.0; N];
+    for (k, value) in row.iter_mut().enumerate() {
+        *value = table[i][k] + (table[i + 1][k] - table[i][k]) * fraction;
+    }
+    row
+}
+
+/// Interpolates the averages and amplitudes by latitude and applies the seasonal variation,
+/// which peaks on day 28 in the northern hemisphere and half a year later in the southern one.
+fn seasonal<const N: usize>(
+    average: &[[f64; N]; 5],
+    amplitude: &[[f64; N]; 5],
+    lat: f64,
+    day_of_year: f64,
+) -> [f64; N] {
+    let day = if lat < 0.0 {
+        day_of_year + 365.25 / 2.0
+    } else {
+        day_of_year
+    };
+    let season = (2.0 * PI * (day - 28.0) / 365.25).cos();
+    let mut row = interpolate(average, lat);
+    for (value, amplitude) in row.iter_mut().zip(interpolate(amplitude, lat)) {
+        *value -= amplitude * season;
+    }
+    row
+}
+
+/// Day of the year, from 1, of a GPS time. Leap seconds don't matter at this precision.
+fn day_of_year(gps_time: f64) -> f64 {
+    // the GPS epoch is January 6, 1980
+    let mut days = gps_time / 86400.0 + 5.0;
+    let mut year = 1980;
+    loop {
+        let length = if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) {
+            366.0
+        } else {
+            365.0
+        };
+        if days < length {
+            return days + 1.0;
+        }
+        days -= length;
+        year += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tropospheric_delay() {
+        // 2020-01-01 00:00
+        assert!((day_of_year(2086.0 * 604800.0 + 3.0 * 86400.0) - 1.0).abs() < 1e-9);
+
+        let sea_level = Geodetic::new(45.0, 0.0, 0.0);
+        let meteo = Meteo::unb3(&sea_level, 28.0);
+        assert!((meteo.pressure - 1018.0).abs() < 1e-9);
+        let (hydrostatic, wet) = meteo.zenith_delays(&sea_level);
+        assert!((hydrostatic - 2.32).abs() < 0.01);
+        assert!(wet > 0.0 && wet < 0.1);
+
+        let (zenith_mapping, _) = niell_mapping(&sea_level, 90.0, 28.0);
+        assert!((zenith_mapping - 1.0).abs() < 1e-6);
+        let (hydrostatic_mapping, wet_mapping) = niell_mapping(&sea_level, 10.0, 28.0);
+        assert!((hydrostatic_mapping - 5.55).abs() < 0.05);
+        assert!((wet_mapping - 5.6).abs() < 0.1);
+
+        let mountain = Geodetic::new(45.0, 0.0, 3000.0);
+        assert!(delay(&mountain, 30.0, 0.0) < delay(&sea_level, 30.0, 0.0));
+    }
+}
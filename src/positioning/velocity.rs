@@ -0,0 +1,70 @@
+use nalgebra::{Matrix4, Vector3, Vector4};
+
+use super::{SolutionError, SPEED_OF_LIGHT};
+use crate::coords::Geodetic;
+
+/// Wavelength of the GPS L1 carrier, in meters.
+pub const L1_WAVELENGTH: f64 = SPEED_OF_LIGHT / 1575.42e6;
+
+/// Receiver velocity and clock drift: least squares fit to the Doppler measurements of the
+/// satellites used in a position solution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VelocitySolution {
+    /// ECEF, in m/s.
+    pub velocity: Vector3<f64>,
+    /// Receiver clock drift, in m/s.
+    pub clock_drift: f64,
+    pub satellites: Vec<u8>,
+}
+
+impl VelocitySolution {
+    /// The north, east and down velocity at the receiver position, in m/s.
+    pub fn ned(&self, receiver: &Geodetic) -> Vector3<f64> {
+        receiver.ecef_to_ned(self.velocity)
+    }
+}
+
+/// The Doppler measurement of a satellite and the satellite's motion.
+pub(super) struct RangeRate {
+    pub sv_id: u8,
+    /// Unit vector from the receiver to the satellite, in ECEF.
+    pub line_of_sight: Vector3<f64>,
+    /// ECEF, in m/s.
+    pub satellite_velocity: Vector3<f64>,
+    /// Satellite clock drift, in s/s.
+    pub satellite_clock_drift: f64,
+    /// Measured rate of change of the pseudorange, in m/s.
+    pub range_rate: f64,
+    /// Variance of the range rate, in m^2/s^2.
+    pub variance: f64,
+}
+
+pub(super) fn solve_velocity(range_rates: &[RangeRate]) -> Result<VelocitySolution, SolutionError> {
+    if range_rates.len() < 4 {
+        return Err(SolutionError::NotEnoughSatellites {
+            available: range_rates.len(),
+        });
+    }
+
+    // the range rate is linear in the receiver velocity and clock drift, no need to iterate
+    let mut normal = Matrix4::zeros();
+    let mut rhs = Vector4::zeros();
+    for range_rate in range_rates {
+        let los = range_rate.line_of_sight;
+        let h = Vector4::new(-los.x, -los.y, -los.z, 1.0);
+        let satellite_range_rate = los.dot(&range_rate.satellite_velocity)
+            - SPEED_OF_LIGHT * range_rate.satellite_clock_drift;
+        let weight = 1.0 / range_rate.variance;
+        normal += h * h.transpose() * weight;
+        rhs += h * (weight * (range_rate.range_rate - satellite_range_rate));
+    }
+    let solution = normal.try_inverse().ok_or(SolutionError::Singular)? * rhs;
+    Ok(VelocitySolution {
+        velocity: solution.xyz(),
+        clock_drift: solution.w,
+        satellites: range_rates
+            .iter()
+            .map(|range_rate| range_rate.sv_id)
+            .collect(),
+    })
+}
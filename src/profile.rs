@@ -0,0 +1,271 @@
+//! Receiver configuration profiles: TOML files describing the port, the measurement rate, the
+//! messages to output and the constellations to track. Everything is optional and defaults to
+//! `ReceiverConfig::default()`, except that a `[port]` section needs its `type`:
+//!
+//! ```toml
+//! gnss = ["gps", "galileo"]
+//! messages = ["RXM-RAWX", "RXM-SFRBX", "NAV-PVT", "NAV-SAT"]
+//!
+//! [port]
+//! type = "uart1"          # usb, uart1, uart2, i2c or spi
+//! baud_rate = 115200      # UART only
+//! # address = 66         # I2C only
+//! input = ["ubx"]         # ubx, nmea, rtcm, rtcm3
+//! output = ["ubx"]        # ubx, nmea, rtcm3
+//!
+//! [rate]
+//! measurement_ms = 1000
+//! navigation_cycles = 1
+//! time_ref = "gps"        # utc, gps, glonass, beidou or galileo
+//! ```
+
+use std::{error::Error, fmt, fs, io, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    config::ReceiverConfig,
+    ublox::{
+        parse_msg_class_id, UbxCfgPrtInMask, UbxCfgPrtOutMask, UbxCfgPrtPort, UbxCfgPrtSettings,
+        UbxCfgRateTimeRef,
+    },
+};
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::Io(err) => write!(f, "couldn't read the profile: {}", err),
+            ProfileError::Parse(err) => write!(f, "invalid profile: {}", err),
+            ProfileError::Invalid(err) => write!(f, "invalid profile: {}", err),
+        }
+    }
+}
+
+impl Error for ProfileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ProfileError::Io(err) => Some(err),
+            ProfileError::Parse(err) => Some(err),
+            ProfileError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for ProfileError {
+    fn from(err: io::Error) -> Self {
+        ProfileError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for ProfileError {
+    fn from(err: toml::de::Error) -> Self {
+        ProfileError::Parse(err)
+    }
+}
+
+impl From<String> for ProfileError {
+    fn from(err: String) -> Self {
+        ProfileError::Invalid(err)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Profile {
+    gnss: Option<Vec<String>>,
+    messages: Option<Vec<String>>,
+    port: Option<PortSection>,
+    #[serde(default)]
+    rate: RateSection,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PortSection {
+    #[serde(rename = "type")]
+    kind: String,
+    baud_rate: Option<u32>,
+    address: Option<u8>,
+    input: Option<Vec<String>>,
+    output: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RateSection {
+    measurement_ms: Option<u16>,
+    navigation_cycles: Option<u16>,
+    time_ref: Option<String>,
+}
+
+/// Parses a profile.
+pub fn parse(text: &str) -> Result<ReceiverConfig, ProfileError> {
+    let profile: Profile = toml::from_str(text)?;
+    let default = ReceiverConfig::default();
+    Ok(ReceiverConfig {
+        port: match profile.port {
+            Some(port) => port_settings(&port)?,
+            None => default.port,
+        },
+        gnss: match profile.gnss {
+            Some(gnss) => gnss
+                .iter()
+                .map(|gnss| gnss.parse())
+                .collect::<Result<_, _>>()?,
+            None => default.gnss,
+        },
+        meas_rate_ms: profile.rate.measurement_ms.unwrap_or(default.meas_rate_ms),
+        nav_rate_cycles: profile
+            .rate
+            .navigation_cycles
+            .unwrap_or(default.nav_rate_cycles),
+        time_ref: match profile.rate.time_ref {
+            Some(time_ref) => parse_time_ref(&time_ref)?,
+            None => default.time_ref,
+        },
+        messages: match profile.messages {
+            Some(messages) => messages
+                .iter()
+                .map(|msg| parse_msg_class_id(msg))
+                .collect::<Result<_, _>>()?,
+            None => default.messages,
+        },
+    })
+}
+
+/// Reads and parses a profile file.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<ReceiverConfig, ProfileError> {
+    parse(&fs::read_to_string(path)?)
+}
+
+fn port_settings(section: &PortSection) -> Result<UbxCfgPrtSettings, String> {
+    let default_protocols = vec!["ubx".to_owned()];
+    let mut in_mask = UbxCfgPrtInMask::empty();
+    for protocol in section.input.as_ref().unwrap_or(&default_protocols) {
+        in_mask |= match protocol.to_ascii_lowercase().as_str() {
+            "ubx" => UbxCfgPrtInMask::UBX,
+            "nmea" => UbxCfgPrtInMask::NMEA,
+            "rtcm" => UbxCfgPrtInMask::RTCM,
+            "rtcm3" => UbxCfgPrtInMask::RTCM3,
+            _ => return Err(format!("unknown input protocol: {}", protocol)),
+        };
+    }
+    let mut out_mask = UbxCfgPrtOutMask::empty();
+    for protocol in section.output.as_ref().unwrap_or(&default_protocols) {
+        out_mask |= match protocol.to_ascii_lowercase().as_str() {
+            "ubx" => UbxCfgPrtOutMask::UBX,
+            "nmea" => UbxCfgPrtOutMask::NMEA,
+            "rtcm3" => UbxCfgPrtOutMask::RTCM3,
+            _ => return Err(format!("unknown output protocol: {}", protocol)),
+        };
+    }
+    if !in_mask.contains(UbxCfgPrtInMask::UBX) || !out_mask.contains(UbxCfgPrtOutMask::UBX) {
+        return Err("UBX has to be enabled on the port".to_owned());
+    }
+
+    let baud_rate = section.baud_rate.unwrap_or(9600);
+    let settings = match section.kind.to_ascii_lowercase().as_str() {
+        "usb" => UbxCfgPrtSettings::usb(in_mask, out_mask),
+        "uart1" => UbxCfgPrtSettings::uart(1, baud_rate, in_mask, out_mask),
+        "uart2" => UbxCfgPrtSettings::uart(2, baud_rate, in_mask, out_mask),
+        "i2c" => UbxCfgPrtSettings::i2c(section.address.unwrap_or(0x42), in_mask, out_mask),
+        "spi" => UbxCfgPrtSettings {
+            port: UbxCfgPrtPort::Spi {
+                mode: 0,
+                flow_control: false,
+                ff_count: 50,
+            },
+            ..UbxCfgPrtSettings::usb(in_mask, out_mask)
+        },
+        _ => return Err(format!("unknown port: {}", section.kind)),
+    };
+    let baud_rate_used = matches!(settings.port, UbxCfgPrtPort::Uart { .. });
+    let address_used = matches!(settings.port, UbxCfgPrtPort::I2c { .. });
+    let unused = match (section.baud_rate, section.address) {
+        (Some(_), _) if !baud_rate_used => Some("baud_rate"),
+        (_, Some(_)) if !address_used => Some("address"),
+        _ => None,
+    };
+    if let Some(key) = unused {
+        return Err(format!("{} doesn't apply to a {} port", key, section.kind));
+    }
+    Ok(settings)
+}
+
+fn parse_time_ref(s: &str) -> Result<UbxCfgRateTimeRef, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "utc" => Ok(UbxCfgRateTimeRef::Utc),
+        "gps" => Ok(UbxCfgRateTimeRef::Gps),
+        "glonass" => Ok(UbxCfgRateTimeRef::Glonass),
+        "beidou" => Ok(UbxCfgRateTimeRef::BeiDou),
+        "galileo" => Ok(UbxCfgRateTimeRef::Galileo),
+        _ => Err(format!("unknown time reference: {}", s)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ublox::{GnssId, UbxNavPvt, UbxRxmRawx};
+
+    #[test]
+    fn parse_profile() {
+        let config = parse(
+            r#"
+            gnss = ["gps", "galileo"]
+            messages = ["RXM-RAWX", "01:07"]
+
+            [port]
+            type = "uart1"
+            baud_rate = 115200
+            input = ["ubx", "nmea"]
+
+            [rate]
+            measurement_ms = 200
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.port,
+            UbxCfgPrtSettings::uart(
+                1,
+                115200,
+                UbxCfgPrtInMask::UBX | UbxCfgPrtInMask::NMEA,
+                UbxCfgPrtOutMask::UBX
+            )
+        );
+        assert_eq!(config.gnss, vec![GnssId::Gps, GnssId::Galileo]);
+        assert_eq!(
+            config.messages,
+            vec![
+                (UbxRxmRawx::CLASS, UbxRxmRawx::ID),
+                (UbxNavPvt::CLASS, UbxNavPvt::ID)
+            ]
+        );
+        assert_eq!(config.meas_rate_ms, 200);
+        assert_eq!(config.nav_rate_cycles, 1);
+
+        assert!(parse("[port]\ntype = \"usb\"\noutput = [\"nmea\"]").is_err());
+        for port in [
+            "type = \"usb\"\nbaud_rate = 115200",
+            "type = \"i2c\"\nbaud_rate = 115200",
+            "type = \"uart1\"\naddress = 66",
+            "type = \"spi\"\naddress = 66",
+        ] {
+            let err = parse(&format!("[port]\n{}", port)).unwrap_err();
+            assert!(matches!(err, ProfileError::Invalid(_)));
+        }
+        assert_eq!(parse("").unwrap(), ReceiverConfig::default());
+
+        let err = parse("[port]\nbaud_rate = 9600").unwrap_err();
+        assert!(matches!(err, ProfileError::Parse(_)));
+        assert!(err.source().is_some());
+    }
+}
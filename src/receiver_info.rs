@@ -0,0 +1,151 @@
+use crate::ublox::{GnssId, UbxMonVer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiverGeneration {
+    U6,
+    U7,
+    M8,
+    F9,
+    M10,
+    Unknown,
+}
+
+/// How the receiver is configured: with the CFG-* messages like CFG-PRT and CFG-GNSS, or with
+/// the configuration database (CFG-VALSET/VALGET) introduced with generation 9.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigInterface {
+    Legacy,
+    ValueDatabase,
+}
+
+/// What the receiver is and what it can do, as reported in MON-VER.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceiverInfo {
+    pub sw_version: String,
+    pub hw_version: String,
+    pub generation: ReceiverGeneration,
+    /// Firmware version, like "SPG 3.01".
+    pub firmware: Option<String>,
+    /// UBX protocol version as (major, minor).
+    pub protocol_version: Option<(u8, u8)>,
+    /// Module name, like "NEO-M8N-0".
+    pub module: Option<String>,
+    /// Constellations the receiver supports. Empty if it doesn't say.
+    pub gnss: Vec<GnssId>,
+}
+
+impl ReceiverInfo {
+    pub fn new(sw_version: &str, hw_version: &str, extensions: &[String]) -> Self {
+        let generation = match u32::from_str_radix(hw_version.trim(), 16) {
+            Ok(0x0004_0007) => ReceiverGeneration::U6,
+            Ok(0x0007_0000) => ReceiverGeneration::U7,
+            Ok(0x0008_0000) => ReceiverGeneration::M8,
+            Ok(0x0019_0000) => ReceiverGeneration::F9,
+            Ok(0x000a_0000) => ReceiverGeneration::M10,
+            _ => ReceiverGeneration::Unknown,
+        };
+        let mut info = ReceiverInfo {
+            sw_version: sw_version.to_owned(),
+            hw_version: hw_version.to_owned(),
+            generation,
+            firmware: None,
+            protocol_version: None,
+            module: None,
+            gnss: vec![],
+        };
+
+        for extension in extensions {
+            // older firmware separates keys with a space instead of '='
+            let (key, value) = match extension.split_once(['=', ' ']) {
+                Some((key, value)) => (key, value.trim()),
+                None => (extension.as_str(), ""),
+            };
+            match key {
+                "FWVER" => info.firmware = Some(value.to_owned()),
+                "PROTVER" => {
+                    info.protocol_version = value
+                        .split_once('.')
+                        .and_then(|(major, minor)| Some((major.parse().ok()?, minor.parse().ok()?)))
+                }
+                "MOD" => info.module = Some(value.to_owned()),
+                _ if !extension.contains('=') => {
+                    // a list of constellations like "GPS;GLO;GAL;BDS" - the same line holds
+                    // augmentation systems like "SBAS;IMES;QZSS"
+                    let gnss: Vec<GnssId> = extension
+                        .split(';')
+                        .filter_map(|name| name.trim().parse().ok())
+                        .collect();
+                    if gnss.len() == extension.split(';').count() {
+                        info.gnss.extend(gnss);
+                    }
+                }
+                _ => {}
+            }
+        }
+        info
+    }
+
+    pub fn from_mon_ver(msg: &UbxMonVer) -> Option<Self> {
+        match msg {
+            UbxMonVer::Poll => None,
+            UbxMonVer::Version {
+                sw_version,
+                hw_version,
+                extensions,
+            } => Some(Self::new(sw_version, hw_version, extensions)),
+        }
+    }
+
+    pub fn config_interface(&self) -> ConfigInterface {
+        match self.generation {
+            ReceiverGeneration::F9 | ReceiverGeneration::M10 => ConfigInterface::ValueDatabase,
+            ReceiverGeneration::Unknown if self.protocol_version >= Some((27, 0)) => {
+                ConfigInterface::ValueDatabase
+            }
+            _ => ConfigInterface::Legacy,
+        }
+    }
+
+    /// Whether the receiver can track the given constellation. Receivers that don't list the
+    /// constellations they support are assumed to support all of them.
+    pub fn supports(&self, gnss: GnssId) -> bool {
+        self.gnss.is_empty() || self.gnss.contains(&gnss)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn info(hw_version: &str, extensions: &[&str]) -> ReceiverInfo {
+        let extensions: Vec<_> = extensions.iter().map(|s| s.to_string()).collect();
+        ReceiverInfo::new("ROM CORE 3.01 (107888)", hw_version, &extensions)
+    }
+
+    #[test]
+    fn m8_capabilities() {
+        let m8 = info(
+            "00080000",
+            &[
+                "FWVER=SPG 3.01",
+                "PROTVER=18.00",
+                "MOD=NEO-M8N-0",
+                "GPS;GLO;GAL;BDS",
+                "SBAS;IMES;QZSS",
+            ],
+        );
+        assert_eq!(m8.generation, ReceiverGeneration::M8);
+        assert_eq!(m8.firmware.as_deref(), Some("SPG 3.01"));
+        assert_eq!(m8.protocol_version, Some((18, 0)));
+        assert_eq!(m8.module.as_deref(), Some("NEO-M8N-0"));
+        assert_eq!(m8.gnss.len(), 7);
+        assert_eq!(m8.config_interface(), ConfigInterface::Legacy);
+
+        let f9 = info(
+            "00190000",
+            &["FWVER=HPG 1.32", "PROTVER=27.31", "GPS;GLO;GAL;BDS"],
+        );
+        assert_eq!(f9.config_interface(), ConfigInterface::ValueDatabase);
+        assert!(!f9.supports(GnssId::Imes));
+    }
+}
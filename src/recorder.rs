@@ -0,0 +1,115 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Writes the raw receiver stream to a `.ubx` log: the bytes exactly as they came from the
+/// receiver, which is the format u-center reads and writes.
+///
+/// Optionally, a sidecar index records when each chunk of the log was received by the host. Each
+/// line of the index is `<offset> <length> <unix time>`, with the time in seconds.
+pub struct Recorder {
+    data: Box<dyn Write + Send>,
+    index: Option<Box<dyn Write + Send>>,
+    offset: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IndexEntry {
+    pub offset: u64,
+    pub length: u64,
+    pub host_time: f64,
+}
+
+impl Recorder {
+    pub fn new<W: Write + Send + 'static>(data: W) -> Self {
+        Recorder {
+            data: Box::new(data),
+            index: None,
+            offset: 0,
+        }
+    }
+
+    pub fn with_index<W: Write + Send + 'static>(mut self, index: W) -> Self {
+        self.index = Some(Box::new(index));
+        self
+    }
+
+    /// Creates the log file and, if requested, the index next to it (see `index_path`).
+    pub fn create<P: AsRef<Path>>(path: P, with_index: bool) -> io::Result<Self> {
+        let path = path.as_ref();
+        let recorder = Self::new(BufWriter::new(File::create(path)?));
+        if with_index {
+            let index = BufWriter::new(File::create(Self::index_path(path))?);
+            Ok(recorder.with_index(index))
+        } else {
+            Ok(recorder)
+        }
+    }
+
+    /// The path of the index for a given log: the log path with `.idx` appended.
+    pub fn index_path<P: AsRef<Path>>(path: P) -> PathBuf {
+        let mut index_path = path.as_ref().as_os_str().to_owned();
+        index_path.push(".idx");
+        index_path.into()
+    }
+
+    /// Reads back an index written by a `Recorder`.
+    pub fn read_index<P: AsRef<Path>>(path: P) -> io::Result<Vec<IndexEntry>> {
+        let invalid = |line: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid index entry: {:?}", line),
+            )
+        };
+        let mut result = vec![];
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            let fields: Vec<_> = line.split_whitespace().collect();
+            match fields[..] {
+                [offset, length, host_time] => result.push(IndexEntry {
+                    offset: offset.parse().map_err(|_| invalid(&line))?,
+                    length: length.parse().map_err(|_| invalid(&line))?,
+                    host_time: host_time.parse().map_err(|_| invalid(&line))?,
+                }),
+                [] => {}
+                _ => return Err(invalid(&line)),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Total number of bytes recorded so far.
+    pub fn bytes_recorded(&self) -> u64 {
+        self.offset
+    }
+
+    /// Appends a chunk of the stream to the log. Everything is flushed right away, so that the
+    /// log is complete even if the process gets killed.
+    pub fn record(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.data.write_all(bytes)?;
+        self.data.flush()?;
+        if let Some(ref mut index) = self.index {
+            let host_time = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_secs_f64();
+            writeln!(index, "{} {} {:.6}", self.offset, bytes.len(), host_time)?;
+            index.flush()?;
+        }
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder")
+            .field("index", &self.index.is_some())
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
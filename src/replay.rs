@@ -0,0 +1,188 @@
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    port_buffer::{Duplex, FramerError, Message, PortBuffer, PortBufferStats},
+    time_source::{ReplayClock, TimeSource},
+    ublox::{ChecksumMode, UbloxMsg},
+};
+
+/// Jumps in the log time larger than this (in seconds) are skipped instead of waited out.
+const MAX_GAP: f64 = 5.0;
+/// The longest time to sleep at once while pacing, so that pausing and seeking stay responsive.
+const MAX_SLEEP: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pacing {
+    /// Return messages as fast as they can be read.
+    AsFastAsPossible,
+    /// Wait between measurement epochs as long as the receiver did, scaled by the clock's speed.
+    RealTime,
+}
+
+#[derive(Debug)]
+pub enum ReplayEvent {
+    Message(Message),
+    Error(FramerError),
+    /// The log was rewound to the beginning to seek backwards - any state built from the
+    /// messages so far should be discarded.
+    Rewound,
+}
+
+/// Plays back a recorded log through the framer. Time in the log is taken from the measurement
+/// epochs in RXM-RAWX messages, and the `ReplayClock` is kept in step with it.
+#[derive(Debug)]
+pub struct Replay<R> {
+    port: PortBuffer<Duplex<R, io::Sink>>,
+    pacing: Pacing,
+    clock: Arc<ReplayClock>,
+    log_time: Option<f64>,
+    seek_target: Option<f64>,
+}
+
+impl Replay<File> {
+    pub fn open<P: AsRef<Path>>(path: P, pacing: Pacing) -> io::Result<Self> {
+        Ok(Self::new(File::open(path)?, pacing))
+    }
+}
+
+impl<R: Read + Seek> Replay<R> {
+    pub fn new(log: R, pacing: Pacing) -> Self {
+        Self {
+            port: PortBuffer::new(Duplex::new(log, io::sink())),
+            pacing,
+            clock: Arc::new(ReplayClock::new()),
+            log_time: None,
+            seek_target: None,
+        }
+    }
+
+    /// The clock following the replay; it also controls pausing, speed and seeking.
+    pub fn clock(&self) -> Arc<ReplayClock> {
+        self.clock.clone()
+    }
+
+    /// The GPS time of the last measurement epoch replayed.
+    pub fn log_time(&self) -> Option<f64> {
+        self.log_time
+    }
+
+    pub fn set_checksum_mode(&mut self, mode: ChecksumMode) {
+        self.port.set_checksum_mode(mode);
+    }
+
+    pub fn stats(&self) -> PortBufferStats {
+        self.port.stats()
+    }
+
+    /// Returns the next message from the log, waiting first if the pacing requires it. Returns
+    /// `None` at the end of the log.
+    pub fn next_event(&mut self) -> io::Result<Option<ReplayEvent>> {
+        if let Some(target) = self.clock.take_seek() {
+            self.seek_target = Some(target);
+            if matches!(self.log_time, Some(t) if target < t) {
+                self.port.get_mut().reader.seek(SeekFrom::Start(0))?;
+                self.port.clear();
+                self.log_time = None;
+                return Ok(Some(ReplayEvent::Rewound));
+            }
+        }
+        loop {
+            match self.port.read_msg() {
+                Some(Ok(msg)) => {
+                    if let Message::Ublox(UbloxMsg::RxmRawx(ref rawx)) = msg {
+                        self.pace(rawx.week as f64 * 604800.0 + rawx.rcv_tow);
+                    }
+                    return Ok(Some(ReplayEvent::Message(msg)));
+                }
+                Some(Err(err)) => return Ok(Some(ReplayEvent::Error(err))),
+                None => {
+                    if self.port.read()? == 0 {
+                        return Ok(None);
+                    }
+                }
+            }
+        }
+    }
+
+    fn pace(&mut self, t: f64) {
+        self.log_time = Some(t);
+        match self.seek_target {
+            Some(target) if t < target => {
+                self.clock.set(t);
+                return;
+            }
+            Some(_) => self.seek_target = None,
+            None => {}
+        }
+        match self.pacing {
+            Pacing::AsFastAsPossible => self.clock.set(t),
+            Pacing::RealTime => {
+                if (t - self.clock.now()).abs() > MAX_GAP {
+                    self.clock.set(t);
+                }
+                while !self.clock.seek_pending() {
+                    let remaining = t - self.clock.now();
+                    if remaining <= 0.0 {
+                        break;
+                    }
+                    let sleep = if self.clock.is_paused() {
+                        MAX_SLEEP
+                    } else {
+                        Duration::from_secs_f64(remaining / self.clock.speed()).min(MAX_SLEEP)
+                    };
+                    thread::sleep(sleep);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::ublox::{UbxRxmRawx, UbxRxmRawxRecvStatus};
+
+    fn epoch(rcv_tow: f64) -> Vec<u8> {
+        let rawx = UbxRxmRawx {
+            rcv_tow,
+            week: 2200,
+            leap_sec: 18,
+            recv_status: UbxRxmRawxRecvStatus::empty(),
+            measurements: vec![],
+        };
+        UbloxMsg::RxmRawx(rawx).into()
+    }
+
+    #[test]
+    fn seek_backwards_rewinds() {
+        let log: Vec<u8> = (0..5).flat_map(|i| epoch(1000.0 + i as f64)).collect();
+        let mut replay = Replay::new(Cursor::new(log), Pacing::AsFastAsPossible);
+        let clock = replay.clock();
+        let mut epochs = 0;
+        while let Some(ReplayEvent::Message(_)) = replay.next_event().unwrap() {
+            epochs += 1;
+        }
+        assert_eq!(epochs, 5);
+        assert!((clock.now() - (2200.0 * 604800.0 + 1004.0)).abs() < 1.0);
+
+        clock.seek(2200.0 * 604800.0 + 1002.0);
+        assert!(matches!(
+            replay.next_event().unwrap(),
+            Some(ReplayEvent::Rewound)
+        ));
+        let mut epochs = 0;
+        while let Some(ReplayEvent::Message(_)) = replay.next_event().unwrap() {
+            epochs += 1;
+        }
+        assert_eq!(epochs, 5);
+    }
+}
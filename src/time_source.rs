@@ -0,0 +1,144 @@
+use std::{
+    fmt,
+    sync::Mutex,
+    time::{Instant, SystemTime},
+};
+
+/// A source of the current time, in seconds. `GpsStatus` uses it to tell the time between
+/// receiver measurements.
+pub trait TimeSource: fmt::Debug + Send + Sync {
+    fn now(&self) -> f64;
+}
+
+/// The host's wall clock, as a Unix timestamp.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl TimeSource for SystemClock {
+    fn now(&self) -> f64 {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs_f64()
+    }
+}
+
+/// The clock of a replayed log. It runs at an adjustable speed from the last time it was set,
+/// can be paused, and carries seek requests to the `Replay` driving it.
+#[derive(Debug)]
+pub struct ReplayClock {
+    state: Mutex<ReplayClockState>,
+}
+
+#[derive(Debug)]
+struct ReplayClockState {
+    time: f64,
+    since: Instant,
+    speed: f64,
+    paused: bool,
+    seek: Option<f64>,
+}
+
+impl ReplayClockState {
+    fn now(&self) -> f64 {
+        if self.paused {
+            self.time
+        } else {
+            self.time + self.since.elapsed().as_secs_f64() * self.speed
+        }
+    }
+
+    fn set(&mut self, time: f64) {
+        self.time = time;
+        self.since = Instant::now();
+    }
+}
+
+impl ReplayClock {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(ReplayClockState {
+                time: 0.0,
+                since: Instant::now(),
+                speed: 1.0,
+                paused: false,
+                seek: None,
+            }),
+        }
+    }
+
+    pub fn set(&self, time: f64) {
+        self.state.lock().unwrap().set(time);
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.state.lock().unwrap().speed
+    }
+
+    pub fn set_speed(&self, speed: f64) {
+        let mut state = self.state.lock().unwrap();
+        let now = state.now();
+        state.set(now);
+        state.speed = speed;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().paused
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        let mut state = self.state.lock().unwrap();
+        let now = state.now();
+        state.set(now);
+        state.paused = paused;
+    }
+
+    pub fn toggle_pause(&self) {
+        let paused = self.is_paused();
+        self.set_paused(!paused);
+    }
+
+    /// Asks the replay to continue from the given time.
+    pub fn seek(&self, time: f64) {
+        self.state.lock().unwrap().seek = Some(time);
+    }
+
+    pub fn seek_pending(&self) -> bool {
+        self.state.lock().unwrap().seek.is_some()
+    }
+
+    pub(crate) fn take_seek(&self) -> Option<f64> {
+        self.state.lock().unwrap().seek.take()
+    }
+}
+
+impl Default for ReplayClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeSource for ReplayClock {
+    fn now(&self) -> f64 {
+        self.state.lock().unwrap().now()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn replay_clock_pause() {
+        let clock = ReplayClock::new();
+        clock.set_speed(1000.0);
+        clock.set(100.0);
+        clock.set_paused(true);
+        let t = clock.now();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        assert_eq!(clock.now(), t);
+        clock.set_paused(false);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        assert!(clock.now() >= t + 5.0);
+    }
+}
@@ -0,0 +1,225 @@
+use std::{error::Error, fmt};
+
+/// Errors produced when decoding UBX frames and payloads.
+///
+/// Offsets are counted in bytes from the start of the message payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UbxError {
+    /// The frame doesn't start with the `0xb5 0x62` sync characters.
+    WrongHeader { found: [u8; 2] },
+    /// The frame is shorter than its header and length field require.
+    FrameTooShort { expected: usize, actual: usize },
+    BadChecksum {
+        class: u8,
+        id: u8,
+        expected: [u8; 2],
+        actual: [u8; 2],
+    },
+    /// The payload is shorter than the minimum for its message type.
+    PayloadTooShort {
+        class: u8,
+        id: u8,
+        expected: usize,
+        actual: usize,
+    },
+    /// The payload length is inconsistent with the contents of the message.
+    WrongLength {
+        class: u8,
+        id: u8,
+        expected: usize,
+        actual: usize,
+    },
+    /// The payload length doesn't match any of the layouts of the message type.
+    UnexpectedLength { class: u8, id: u8, actual: usize },
+    UnknownGnssId {
+        class: u8,
+        id: u8,
+        offset: usize,
+        value: u8,
+    },
+    InvalidField {
+        class: u8,
+        id: u8,
+        offset: usize,
+        field: &'static str,
+        value: u32,
+    },
+}
+
+impl UbxError {
+    /// The class and ID of the message the error refers to, if known.
+    pub fn msg_class_id(&self) -> Option<(u8, u8)> {
+        match *self {
+            UbxError::WrongHeader { .. } | UbxError::FrameTooShort { .. } => None,
+            UbxError::BadChecksum { class, id, .. }
+            | UbxError::PayloadTooShort { class, id, .. }
+            | UbxError::WrongLength { class, id, .. }
+            | UbxError::UnexpectedLength { class, id, .. }
+            | UbxError::UnknownGnssId { class, id, .. }
+            | UbxError::InvalidField { class, id, .. } => Some((class, id)),
+        }
+    }
+
+    /// The payload offset of the offending field, if the error refers to a single field.
+    pub fn offset(&self) -> Option<usize> {
+        match *self {
+            UbxError::UnknownGnssId { offset, .. } | UbxError::InvalidField { offset, .. } => {
+                Some(offset)
+            }
+            _ => None,
+        }
+    }
+
+    /// Moves the offset of a field error by `base` bytes - used when a sub-structure is decoded
+    /// from a slice of the payload.
+    pub(crate) fn shifted(mut self, base: usize) -> Self {
+        match &mut self {
+            UbxError::UnknownGnssId { offset, .. } | UbxError::InvalidField { offset, .. } => {
+                *offset += base;
+            }
+            _ => {}
+        }
+        self
+    }
+}
+
+impl fmt::Display for UbxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UbxError::WrongHeader { found } => {
+                write!(f, "wrong header: expected [181, 98], got {:?}", found)
+            }
+            UbxError::FrameTooShort { expected, actual } => write!(
+                f,
+                "frame too short: expected {} bytes, got {}",
+                expected, actual
+            ),
+            UbxError::BadChecksum {
+                class,
+                id,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "0x{:02x} 0x{:02x}: bad checksum: expected {:?}, got {:?}",
+                class, id, expected, actual
+            ),
+            UbxError::PayloadTooShort {
+                class,
+                id,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "0x{:02x} 0x{:02x}: payload too short: expected at least {} bytes, got {}",
+                class, id, expected, actual
+            ),
+            UbxError::WrongLength {
+                class,
+                id,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "0x{:02x} 0x{:02x}: wrong payload length: expected {} bytes, got {}",
+                class, id, expected, actual
+            ),
+            UbxError::UnexpectedLength { class, id, actual } => write!(
+                f,
+                "0x{:02x} 0x{:02x}: unexpected payload length: {}",
+                class, id, actual
+            ),
+            UbxError::UnknownGnssId {
+                class,
+                id,
+                offset,
+                value,
+            } => write!(
+                f,
+                "0x{:02x} 0x{:02x}: unknown GNSS ID {} at offset {}",
+                class, id, value, offset
+            ),
+            UbxError::InvalidField {
+                class,
+                id,
+                offset,
+                field,
+                value,
+            } => write!(
+                f,
+                "0x{:02x} 0x{:02x}: invalid value for {} at offset {}: {}",
+                class, id, field, offset, value
+            ),
+        }
+    }
+}
+
+impl Error for UbxError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn field_errors() {
+        let err = UbxError::InvalidField {
+            class: 0x01,
+            id: 0x07,
+            offset: 3,
+            field: "flags",
+            value: 0xff,
+        };
+        assert_eq!(err.msg_class_id(), Some((0x01, 0x07)));
+        assert_eq!(err.offset(), Some(3));
+        let err = err.shifted(8);
+        assert_eq!(err.offset(), Some(11));
+        assert_eq!(
+            err.to_string(),
+            "0x01 0x07: invalid value for flags at offset 11: 255"
+        );
+
+        let err = UbxError::UnknownGnssId {
+            class: 0x02,
+            id: 0x15,
+            offset: 20,
+            value: 9,
+        };
+        assert_eq!(err.clone().shifted(16).offset(), Some(36));
+        assert_eq!(err.to_string(), "0x02 0x15: unknown GNSS ID 9 at offset 20");
+
+        // errors not about a single field don't move
+        let err = UbxError::WrongLength {
+            class: 0x01,
+            id: 0x35,
+            expected: 20,
+            actual: 19,
+        };
+        assert_eq!(err.offset(), None);
+        assert_eq!(err.clone().shifted(8), err);
+        assert_eq!(
+            err.to_string(),
+            "0x01 0x35: wrong payload length: expected 20 bytes, got 19"
+        );
+
+        let err = UbxError::WrongHeader {
+            found: [0xb5, 0x00],
+        };
+        assert_eq!(err.msg_class_id(), None);
+        assert_eq!(err.offset(), None);
+        assert_eq!(
+            err.to_string(),
+            "wrong header: expected [181, 98], got [181, 0]"
+        );
+        let err = UbxError::BadChecksum {
+            class: 0x06,
+            id: 0x00,
+            expected: [1, 2],
+            actual: [3, 4],
+        };
+        assert_eq!(err.msg_class_id(), Some((0x06, 0x00)));
+        assert_eq!(
+            err.to_string(),
+            "0x06 0x00: bad checksum: expected [1, 2], got [3, 4]"
+        );
+    }
+}
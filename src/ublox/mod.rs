@@ -1,6 +1,8 @@
+mod error;
 mod msg;
 mod msg_types;
 mod raw_msg;
 
+pub use error::*;
 pub use msg::*;
 pub use raw_msg::*;
@@ -1,6 +1,56 @@
 use std::convert::{TryFrom, TryInto};
 
-pub use super::{msg_types::*, UbloxRawMsg};
+pub use super::{msg_types::*, UbloxRawMsg, UbxError};
+
+const MSG_NAMES: &[(&str, u8, u8)] = &[
+    ("RXM-SFRBX", UbxRxmSfrbx::CLASS, UbxRxmSfrbx::ID),
+    ("RXM-RAWX", UbxRxmRawx::CLASS, UbxRxmRawx::ID),
+    ("NAV-PVT", UbxNavPvt::CLASS, UbxNavPvt::ID),
+    ("NAV-SAT", UbxNavSat::CLASS, UbxNavSat::ID),
+    ("ACK-ACK", UbxAckAck::CLASS, UbxAckAck::ID),
+    ("ACK-NAK", UbxAckNak::CLASS, UbxAckNak::ID),
+    ("MON-VER", UbxMonVer::CLASS, UbxMonVer::ID),
+    ("CFG-PRT", UbxCfgPrt::CLASS, UbxCfgPrt::ID),
+    ("CFG-MSG", UbxCfgMsg::CLASS, UbxCfgMsg::ID),
+    ("CFG-RATE", UbxCfgRate::CLASS, UbxCfgRate::ID),
+    ("CFG-GNSS", UbxCfgGnss::CLASS, UbxCfgGnss::ID),
+    ("CFG-VALSET", UbxCfgValSet::CLASS, UbxCfgValSet::ID),
+    ("CFG-VALGET", UbxCfgValGet::CLASS, UbxCfgValGet::ID),
+    ("CFG-VALDEL", UbxCfgValDel::CLASS, UbxCfgValDel::ID),
+    ("CFG-CFG", UbxCfgCfg::CLASS, UbxCfgCfg::ID),
+    ("CFG-RST", UbxCfgRst::CLASS, UbxCfgRst::ID),
+];
+
+/// The u-blox name of a message, like "RXM-RAWX".
+pub fn msg_name(class: u8, id: u8) -> Option<&'static str> {
+    MSG_NAMES
+        .iter()
+        .find(|(_, c, i)| *c == class && *i == id)
+        .map(|(name, _, _)| *name)
+}
+
+/// The class and ID of a message by its u-blox name, case-insensitive.
+pub fn msg_class_id(name: &str) -> Option<(u8, u8)> {
+    MSG_NAMES
+        .iter()
+        .find(|(n, _, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, class, id)| (*class, *id))
+}
+
+/// Parses a message given by name (like "RXM-RAWX") or as CLASS:ID in hex (like "02:15").
+pub fn parse_msg_class_id(s: &str) -> Result<(u8, u8), String> {
+    if let Some(class_id) = msg_class_id(s) {
+        return Ok(class_id);
+    }
+    let parse_hex = |hex: &str| u8::from_str_radix(hex.trim_start_matches("0x"), 16);
+    match s.split_once(':') {
+        Some((class, id)) => match (parse_hex(class), parse_hex(id)) {
+            (Ok(class), Ok(id)) => Ok((class, id)),
+            _ => Err(format!("invalid message class/ID: {}", s)),
+        },
+        None => Err(format!("unknown message: {}", s)),
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum UbloxMsg {
@@ -8,50 +58,128 @@ pub enum UbloxMsg {
     CfgPrt(UbxCfgPrt),
     CfgRate(UbxCfgRate),
     CfgGnss(UbxCfgGnss),
+    CfgCfg(UbxCfgCfg),
+    CfgRst(UbxCfgRst),
+    CfgValSet(UbxCfgValSet),
+    CfgValGet(UbxCfgValGet),
+    CfgValDel(UbxCfgValDel),
     RxmSfrbx(UbxRxmSfrbx),
     RxmRawx(UbxRxmRawx),
-    //RxmSfrbx(UbxRxmSfrbx),
+    NavPvt(UbxNavPvt),
+    NavSat(UbxNavSat),
+    AckAck(UbxAckAck),
+    AckNak(UbxAckNak),
+    MonVer(UbxMonVer),
     Other(UbloxRawMsg),
 }
 
+impl UbloxMsg {
+    pub fn class_id(&self) -> (u8, u8) {
+        match self {
+            UbloxMsg::CfgMsg(_) => (UbxCfgMsg::CLASS, UbxCfgMsg::ID),
+            UbloxMsg::CfgPrt(_) => (UbxCfgPrt::CLASS, UbxCfgPrt::ID),
+            UbloxMsg::CfgRate(_) => (UbxCfgRate::CLASS, UbxCfgRate::ID),
+            UbloxMsg::CfgGnss(_) => (UbxCfgGnss::CLASS, UbxCfgGnss::ID),
+            UbloxMsg::CfgCfg(_) => (UbxCfgCfg::CLASS, UbxCfgCfg::ID),
+            UbloxMsg::CfgRst(_) => (UbxCfgRst::CLASS, UbxCfgRst::ID),
+            UbloxMsg::CfgValSet(_) => (UbxCfgValSet::CLASS, UbxCfgValSet::ID),
+            UbloxMsg::CfgValGet(_) => (UbxCfgValGet::CLASS, UbxCfgValGet::ID),
+            UbloxMsg::CfgValDel(_) => (UbxCfgValDel::CLASS, UbxCfgValDel::ID),
+            UbloxMsg::RxmSfrbx(_) => (UbxRxmSfrbx::CLASS, UbxRxmSfrbx::ID),
+            UbloxMsg::RxmRawx(_) => (UbxRxmRawx::CLASS, UbxRxmRawx::ID),
+            UbloxMsg::NavPvt(_) => (UbxNavPvt::CLASS, UbxNavPvt::ID),
+            UbloxMsg::NavSat(_) => (UbxNavSat::CLASS, UbxNavSat::ID),
+            UbloxMsg::AckAck(_) => (UbxAckAck::CLASS, UbxAckAck::ID),
+            UbloxMsg::AckNak(_) => (UbxAckNak::CLASS, UbxAckNak::ID),
+            UbloxMsg::MonVer(_) => (UbxMonVer::CLASS, UbxMonVer::ID),
+            UbloxMsg::Other(raw_msg) => (raw_msg.class(), raw_msg.id()),
+        }
+    }
+
+    pub fn name(&self) -> Option<&'static str> {
+        let (class, id) = self.class_id();
+        msg_name(class, id)
+    }
+}
+
 impl TryFrom<UbloxRawMsg> for UbloxMsg {
-    type Error = String;
+    type Error = UbxError;
 
-    fn try_from(raw_msg: UbloxRawMsg) -> Result<UbloxMsg, String> {
+    fn try_from(raw_msg: UbloxRawMsg) -> Result<UbloxMsg, UbxError> {
         match (raw_msg.class(), raw_msg.id()) {
-            (0x02, 0x13) => {
+            (UbxRxmSfrbx::CLASS, UbxRxmSfrbx::ID) => {
                 let inner = UbxRxmSfrbx::try_from(raw_msg.take_payload())?;
                 Ok(UbloxMsg::RxmSfrbx(inner))
             }
-            (0x02, 0x15) => {
+            (UbxRxmRawx::CLASS, UbxRxmRawx::ID) => {
                 let inner = UbxRxmRawx::try_from(raw_msg.take_payload())?;
                 Ok(UbloxMsg::RxmRawx(inner))
             }
-            (0x06, 0x00) => {
+            (UbxNavPvt::CLASS, UbxNavPvt::ID) => {
+                let inner = UbxNavPvt::try_from(raw_msg.take_payload())?;
+                Ok(UbloxMsg::NavPvt(inner))
+            }
+            (UbxNavSat::CLASS, UbxNavSat::ID) => {
+                let inner = UbxNavSat::try_from(raw_msg.take_payload())?;
+                Ok(UbloxMsg::NavSat(inner))
+            }
+            (UbxAckAck::CLASS, UbxAckAck::ID) => {
+                let inner = UbxAckAck::try_from(raw_msg.take_payload())?;
+                Ok(UbloxMsg::AckAck(inner))
+            }
+            (UbxAckNak::CLASS, UbxAckNak::ID) => {
+                let inner = UbxAckNak::try_from(raw_msg.take_payload())?;
+                Ok(UbloxMsg::AckNak(inner))
+            }
+            (UbxMonVer::CLASS, UbxMonVer::ID) => {
+                let inner = UbxMonVer::try_from(raw_msg.take_payload())?;
+                Ok(UbloxMsg::MonVer(inner))
+            }
+            (UbxCfgPrt::CLASS, UbxCfgPrt::ID) => {
                 let inner = UbxCfgPrt::try_from(raw_msg.take_payload())?;
                 Ok(UbloxMsg::CfgPrt(inner))
             }
-            (0x06, 0x01) => {
+            (UbxCfgMsg::CLASS, UbxCfgMsg::ID) => {
                 let inner = UbxCfgMsg::try_from(raw_msg.take_payload())?;
                 Ok(UbloxMsg::CfgMsg(inner))
             }
-            (0x06, 0x08) => {
+            (UbxCfgRate::CLASS, UbxCfgRate::ID) => {
                 let inner = UbxCfgRate::try_from(raw_msg.take_payload())?;
                 Ok(UbloxMsg::CfgRate(inner))
             }
-            (0x06, 0x3e) => {
+            (UbxCfgGnss::CLASS, UbxCfgGnss::ID) => {
                 let inner = UbxCfgGnss::try_from(raw_msg.take_payload())?;
                 Ok(UbloxMsg::CfgGnss(inner))
             }
+            (UbxCfgCfg::CLASS, UbxCfgCfg::ID) => {
+                let inner = UbxCfgCfg::try_from(raw_msg.take_payload())?;
+                Ok(UbloxMsg::CfgCfg(inner))
+            }
+            (UbxCfgRst::CLASS, UbxCfgRst::ID) => {
+                let inner = UbxCfgRst::try_from(raw_msg.take_payload())?;
+                Ok(UbloxMsg::CfgRst(inner))
+            }
+            (UbxCfgValSet::CLASS, UbxCfgValSet::ID) => {
+                let inner = UbxCfgValSet::try_from(raw_msg.take_payload())?;
+                Ok(UbloxMsg::CfgValSet(inner))
+            }
+            (UbxCfgValGet::CLASS, UbxCfgValGet::ID) => {
+                let inner = UbxCfgValGet::try_from(raw_msg.take_payload())?;
+                Ok(UbloxMsg::CfgValGet(inner))
+            }
+            (UbxCfgValDel::CLASS, UbxCfgValDel::ID) => {
+                let inner = UbxCfgValDel::try_from(raw_msg.take_payload())?;
+                Ok(UbloxMsg::CfgValDel(inner))
+            }
             _ => Ok(UbloxMsg::Other(raw_msg)),
         }
     }
 }
 
 impl TryFrom<Vec<u8>> for UbloxMsg {
-    type Error = String;
+    type Error = UbxError;
 
-    fn try_from(bytes: Vec<u8>) -> Result<UbloxMsg, String> {
+    fn try_from(bytes: Vec<u8>) -> Result<UbloxMsg, UbxError> {
         let raw_msg: UbloxRawMsg = bytes.try_into()?;
         raw_msg.try_into()
     }
@@ -60,12 +188,54 @@ impl TryFrom<Vec<u8>> for UbloxMsg {
 impl From<UbloxMsg> for UbloxRawMsg {
     fn from(msg: UbloxMsg) -> UbloxRawMsg {
         match msg {
-            UbloxMsg::RxmSfrbx(inner) => UbloxRawMsg::new(0x02, 0x13, inner.into()),
-            UbloxMsg::RxmRawx(inner) => UbloxRawMsg::new(0x02, 0x15, inner.into()),
-            UbloxMsg::CfgPrt(inner) => UbloxRawMsg::new(0x06, 0x00, inner.into()),
-            UbloxMsg::CfgMsg(inner) => UbloxRawMsg::new(0x06, 0x01, inner.into()),
-            UbloxMsg::CfgRate(inner) => UbloxRawMsg::new(0x06, 0x08, inner.into()),
-            UbloxMsg::CfgGnss(inner) => UbloxRawMsg::new(0x06, 0x3e, inner.into()),
+            UbloxMsg::RxmSfrbx(inner) => {
+                UbloxRawMsg::new(UbxRxmSfrbx::CLASS, UbxRxmSfrbx::ID, inner.into())
+            }
+            UbloxMsg::RxmRawx(inner) => {
+                UbloxRawMsg::new(UbxRxmRawx::CLASS, UbxRxmRawx::ID, inner.into())
+            }
+            UbloxMsg::NavPvt(inner) => {
+                UbloxRawMsg::new(UbxNavPvt::CLASS, UbxNavPvt::ID, inner.into())
+            }
+            UbloxMsg::NavSat(inner) => {
+                UbloxRawMsg::new(UbxNavSat::CLASS, UbxNavSat::ID, inner.into())
+            }
+            UbloxMsg::AckAck(inner) => {
+                UbloxRawMsg::new(UbxAckAck::CLASS, UbxAckAck::ID, inner.into())
+            }
+            UbloxMsg::AckNak(inner) => {
+                UbloxRawMsg::new(UbxAckNak::CLASS, UbxAckNak::ID, inner.into())
+            }
+            UbloxMsg::MonVer(inner) => {
+                UbloxRawMsg::new(UbxMonVer::CLASS, UbxMonVer::ID, inner.into())
+            }
+            UbloxMsg::CfgPrt(inner) => {
+                UbloxRawMsg::new(UbxCfgPrt::CLASS, UbxCfgPrt::ID, inner.into())
+            }
+            UbloxMsg::CfgMsg(inner) => {
+                UbloxRawMsg::new(UbxCfgMsg::CLASS, UbxCfgMsg::ID, inner.into())
+            }
+            UbloxMsg::CfgRate(inner) => {
+                UbloxRawMsg::new(UbxCfgRate::CLASS, UbxCfgRate::ID, inner.into())
+            }
+            UbloxMsg::CfgGnss(inner) => {
+                UbloxRawMsg::new(UbxCfgGnss::CLASS, UbxCfgGnss::ID, inner.into())
+            }
+            UbloxMsg::CfgCfg(inner) => {
+                UbloxRawMsg::new(UbxCfgCfg::CLASS, UbxCfgCfg::ID, inner.into())
+            }
+            UbloxMsg::CfgRst(inner) => {
+                UbloxRawMsg::new(UbxCfgRst::CLASS, UbxCfgRst::ID, inner.into())
+            }
+            UbloxMsg::CfgValSet(inner) => {
+                UbloxRawMsg::new(UbxCfgValSet::CLASS, UbxCfgValSet::ID, inner.into())
+            }
+            UbloxMsg::CfgValGet(inner) => {
+                UbloxRawMsg::new(UbxCfgValGet::CLASS, UbxCfgValGet::ID, inner.into())
+            }
+            UbloxMsg::CfgValDel(inner) => {
+                UbloxRawMsg::new(UbxCfgValDel::CLASS, UbxCfgValDel::ID, inner.into())
+            }
             UbloxMsg::Other(raw_msg) => raw_msg,
         }
     }
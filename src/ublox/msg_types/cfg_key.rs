@@ -0,0 +1,244 @@
+use std::fmt;
+
+use crate::ublox::{msg_name, GnssId};
+
+/// A configuration item ID, as used by CFG-VALSET, CFG-VALGET and CFG-VALDEL. The storage size
+/// of the value is encoded in bits 28-30 of the ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CfgKey(pub u32);
+
+/// The type of a configuration value, as named in the u-blox interface descriptions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CfgType {
+    L,
+    U1,
+    U2,
+    U4,
+    U8,
+    I1,
+    I2,
+    I4,
+    I8,
+    E1,
+    E2,
+    E4,
+    X1,
+    X2,
+    X4,
+    X8,
+    R4,
+    R8,
+}
+
+const CFG_KEY_DB: &[(&str, u32, CfgType)] = &[
+    ("CFG-RATE-MEAS", 0x30210001, CfgType::U2),
+    ("CFG-RATE-NAV", 0x30210002, CfgType::U2),
+    ("CFG-RATE-TIMEREF", 0x20210003, CfgType::E1),
+    ("CFG-NAVSPG-DYNMODEL", 0x20110021, CfgType::E1),
+    ("CFG-SIGNAL-GPS_ENA", 0x1031001f, CfgType::L),
+    ("CFG-SIGNAL-GPS_L1CA_ENA", 0x10310001, CfgType::L),
+    ("CFG-SIGNAL-GPS_L2C_ENA", 0x10310003, CfgType::L),
+    ("CFG-SIGNAL-SBAS_ENA", 0x10310020, CfgType::L),
+    ("CFG-SIGNAL-SBAS_L1CA_ENA", 0x10310005, CfgType::L),
+    ("CFG-SIGNAL-GAL_ENA", 0x10310021, CfgType::L),
+    ("CFG-SIGNAL-GAL_E1_ENA", 0x10310007, CfgType::L),
+    ("CFG-SIGNAL-BDS_ENA", 0x10310022, CfgType::L),
+    ("CFG-SIGNAL-BDS_B1_ENA", 0x1031000d, CfgType::L),
+    ("CFG-SIGNAL-QZSS_ENA", 0x10310024, CfgType::L),
+    ("CFG-SIGNAL-QZSS_L1CA_ENA", 0x10310012, CfgType::L),
+    ("CFG-SIGNAL-GLO_ENA", 0x10310025, CfgType::L),
+    ("CFG-SIGNAL-GLO_L1_ENA", 0x10310018, CfgType::L),
+    ("CFG-MSGOUT-UBX_NAV_PVT_I2C", 0x20910006, CfgType::U1),
+    ("CFG-MSGOUT-UBX_NAV_PVT_UART1", 0x20910007, CfgType::U1),
+    ("CFG-MSGOUT-UBX_NAV_PVT_UART2", 0x20910008, CfgType::U1),
+    ("CFG-MSGOUT-UBX_NAV_PVT_USB", 0x20910009, CfgType::U1),
+    ("CFG-MSGOUT-UBX_NAV_PVT_SPI", 0x2091000a, CfgType::U1),
+    ("CFG-MSGOUT-UBX_NAV_SAT_I2C", 0x20910015, CfgType::U1),
+    ("CFG-MSGOUT-UBX_NAV_SAT_UART1", 0x20910016, CfgType::U1),
+    ("CFG-MSGOUT-UBX_NAV_SAT_UART2", 0x20910017, CfgType::U1),
+    ("CFG-MSGOUT-UBX_NAV_SAT_USB", 0x20910018, CfgType::U1),
+    ("CFG-MSGOUT-UBX_NAV_SAT_SPI", 0x20910019, CfgType::U1),
+    ("CFG-MSGOUT-UBX_RXM_RAWX_I2C", 0x209102a4, CfgType::U1),
+    ("CFG-MSGOUT-UBX_RXM_RAWX_UART1", 0x209102a5, CfgType::U1),
+    ("CFG-MSGOUT-UBX_RXM_RAWX_UART2", 0x209102a6, CfgType::U1),
+    ("CFG-MSGOUT-UBX_RXM_RAWX_USB", 0x209102a7, CfgType::U1),
+    ("CFG-MSGOUT-UBX_RXM_RAWX_SPI", 0x209102a8, CfgType::U1),
+    ("CFG-MSGOUT-UBX_RXM_SFRBX_I2C", 0x20910231, CfgType::U1),
+    ("CFG-MSGOUT-UBX_RXM_SFRBX_UART1", 0x20910232, CfgType::U1),
+    ("CFG-MSGOUT-UBX_RXM_SFRBX_UART2", 0x20910233, CfgType::U1),
+    ("CFG-MSGOUT-UBX_RXM_SFRBX_USB", 0x20910234, CfgType::U1),
+    ("CFG-MSGOUT-UBX_RXM_SFRBX_SPI", 0x20910235, CfgType::U1),
+    ("CFG-I2CINPROT-UBX", 0x10710001, CfgType::L),
+    ("CFG-I2CINPROT-NMEA", 0x10710002, CfgType::L),
+    ("CFG-I2COUTPROT-UBX", 0x10720001, CfgType::L),
+    ("CFG-I2COUTPROT-NMEA", 0x10720002, CfgType::L),
+    ("CFG-UART1-BAUDRATE", 0x40520001, CfgType::U4),
+    ("CFG-UART1INPROT-UBX", 0x10730001, CfgType::L),
+    ("CFG-UART1INPROT-NMEA", 0x10730002, CfgType::L),
+    ("CFG-UART1INPROT-RTCM3X", 0x10730004, CfgType::L),
+    ("CFG-UART1OUTPROT-UBX", 0x10740001, CfgType::L),
+    ("CFG-UART1OUTPROT-NMEA", 0x10740002, CfgType::L),
+    ("CFG-UART1OUTPROT-RTCM3X", 0x10740004, CfgType::L),
+    ("CFG-UART2-BAUDRATE", 0x40530001, CfgType::U4),
+    ("CFG-UART2INPROT-UBX", 0x10750001, CfgType::L),
+    ("CFG-UART2INPROT-NMEA", 0x10750002, CfgType::L),
+    ("CFG-UART2INPROT-RTCM3X", 0x10750004, CfgType::L),
+    ("CFG-UART2OUTPROT-UBX", 0x10760001, CfgType::L),
+    ("CFG-UART2OUTPROT-NMEA", 0x10760002, CfgType::L),
+    ("CFG-UART2OUTPROT-RTCM3X", 0x10760004, CfgType::L),
+    ("CFG-USBINPROT-UBX", 0x10770001, CfgType::L),
+    ("CFG-USBINPROT-NMEA", 0x10770002, CfgType::L),
+    ("CFG-USBINPROT-RTCM3X", 0x10770004, CfgType::L),
+    ("CFG-USBOUTPROT-UBX", 0x10780001, CfgType::L),
+    ("CFG-USBOUTPROT-NMEA", 0x10780002, CfgType::L),
+    ("CFG-USBOUTPROT-RTCM3X", 0x10780004, CfgType::L),
+    ("CFG-SPIINPROT-UBX", 0x10790001, CfgType::L),
+    ("CFG-SPIINPROT-NMEA", 0x10790002, CfgType::L),
+    ("CFG-SPIOUTPROT-UBX", 0x107a0001, CfgType::L),
+    ("CFG-SPIOUTPROT-NMEA", 0x107a0002, CfgType::L),
+];
+
+impl CfgKey {
+    pub const RATE_MEAS: CfgKey = CfgKey(0x30210001);
+    pub const RATE_NAV: CfgKey = CfgKey(0x30210002);
+    pub const RATE_TIMEREF: CfgKey = CfgKey(0x20210003);
+    pub const SIGNAL_GPS_ENA: CfgKey = CfgKey(0x1031001f);
+    pub const SIGNAL_SBAS_ENA: CfgKey = CfgKey(0x10310020);
+    pub const SIGNAL_GAL_ENA: CfgKey = CfgKey(0x10310021);
+    pub const SIGNAL_BDS_ENA: CfgKey = CfgKey(0x10310022);
+    pub const SIGNAL_QZSS_ENA: CfgKey = CfgKey(0x10310024);
+    pub const SIGNAL_GLO_ENA: CfgKey = CfgKey(0x10310025);
+    pub const USBINPROT_UBX: CfgKey = CfgKey(0x10770001);
+    pub const USBINPROT_NMEA: CfgKey = CfgKey(0x10770002);
+    pub const USBOUTPROT_UBX: CfgKey = CfgKey(0x10780001);
+    pub const USBOUTPROT_NMEA: CfgKey = CfgKey(0x10780002);
+    pub const UART1_BAUDRATE: CfgKey = CfgKey(0x40520001);
+    /// Wildcard matching every item, for reading back the whole configuration.
+    pub const ALL: CfgKey = CfgKey(0x0fffffff);
+
+    /// Size of the value in bytes - single bits take up a whole byte. `None` for IDs with an
+    /// invalid size field, which includes wildcards.
+    pub fn size(&self) -> Option<usize> {
+        match (self.0 >> 28) & 0x07 {
+            1 | 2 => Some(1),
+            3 => Some(2),
+            4 => Some(4),
+            5 => Some(8),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> Option<&'static str> {
+        CFG_KEY_DB
+            .iter()
+            .find(|(_, key, _)| *key == self.0)
+            .map(|(name, _, _)| *name)
+    }
+
+    /// Looks up a key by its u-blox name, like "CFG-RATE-MEAS", case-insensitive.
+    pub fn from_name(name: &str) -> Option<CfgKey> {
+        CFG_KEY_DB
+            .iter()
+            .find(|(n, _, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, key, _)| CfgKey(*key))
+    }
+
+    pub fn value_type(&self) -> Option<CfgType> {
+        CFG_KEY_DB
+            .iter()
+            .find(|(_, key, _)| *key == self.0)
+            .map(|(_, _, value_type)| *value_type)
+    }
+
+    /// The key enabling a constellation. IMES has none.
+    pub fn signal_enable(gnss: GnssId) -> Option<CfgKey> {
+        match gnss {
+            GnssId::Gps => Some(Self::SIGNAL_GPS_ENA),
+            GnssId::Sbas => Some(Self::SIGNAL_SBAS_ENA),
+            GnssId::Galileo => Some(Self::SIGNAL_GAL_ENA),
+            GnssId::BeiDou => Some(Self::SIGNAL_BDS_ENA),
+            GnssId::Imes => None,
+            GnssId::Qzss => Some(Self::SIGNAL_QZSS_ENA),
+            GnssId::Glonass => Some(Self::SIGNAL_GLO_ENA),
+        }
+    }
+
+    /// The key enabling output of a UBX message on a port, named as in the keys (`USB`, `UART1`,
+    /// `I2C`...), if it's in the database.
+    pub fn msgout(class: u8, id: u8, port: &str) -> Option<CfgKey> {
+        let msg_name = msg_name(class, id)?.replace('-', "_");
+        Self::from_name(&format!("CFG-MSGOUT-UBX_{}_{}", msg_name, port))
+    }
+}
+
+impl fmt::Display for CfgKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "0x{:08x}", self.0),
+        }
+    }
+}
+
+/// A configuration item: a key and its raw value, stored in the low `key.size()` bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CfgItem {
+    pub key: CfgKey,
+    pub value: u64,
+}
+
+impl CfgItem {
+    pub fn new(key: CfgKey, value: u64) -> Self {
+        Self { key, value }
+    }
+
+    pub fn bool(key: CfgKey, value: bool) -> Self {
+        Self::new(key, value as u64)
+    }
+
+    /// A floating point value, stored as R4 or R8 depending on the key.
+    pub fn float(key: CfgKey, value: f64) -> Self {
+        match key.size() {
+            Some(4) => Self::new(key, (value as f32).to_bits() as u64),
+            _ => Self::new(key, value.to_bits()),
+        }
+    }
+
+    pub fn as_bool(&self) -> bool {
+        self.value != 0
+    }
+
+    /// The value as a signed integer, sign-extended from the key's storage size.
+    pub fn as_i64(&self) -> i64 {
+        let bits = 8 * self.key.size().unwrap_or(8) as u32;
+        ((self.value << (64 - bits)) as i64) >> (64 - bits)
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        match self.key.size() {
+            Some(4) => f32::from_bits(self.value as u32) as f64,
+            _ => f64::from_bits(self.value),
+        }
+    }
+
+    /// The value formatted according to the type of the key.
+    pub fn value_string(&self) -> String {
+        match self.key.value_type() {
+            Some(CfgType::L) => self.as_bool().to_string(),
+            Some(CfgType::I1 | CfgType::I2 | CfgType::I4 | CfgType::I8) => {
+                self.as_i64().to_string()
+            }
+            Some(CfgType::R4 | CfgType::R8) => self.as_f64().to_string(),
+            Some(CfgType::X1 | CfgType::X2 | CfgType::X4 | CfgType::X8) | None => {
+                format!("0x{:x}", self.value)
+            }
+            Some(_) => self.value.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for CfgItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {}", self.key, self.value_string())
+    }
+}
@@ -1,4 +1,4 @@
-use std::convert::TryFrom;
+use std::{convert::TryFrom, fmt, str::FromStr};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GnssId {
@@ -11,10 +11,12 @@ pub enum GnssId {
     Glonass = 6,
 }
 
+/// Fails with the rejected value, which the message decoders turn into
+/// `UbxError::UnknownGnssId`.
 impl TryFrom<u8> for GnssId {
-    type Error = String;
+    type Error = u8;
 
-    fn try_from(val: u8) -> Result<GnssId, String> {
+    fn try_from(val: u8) -> Result<GnssId, u8> {
         match val {
             0 => Ok(GnssId::Gps),
             1 => Ok(GnssId::Sbas),
@@ -23,7 +25,39 @@ impl TryFrom<u8> for GnssId {
             4 => Ok(GnssId::Imes),
             5 => Ok(GnssId::Qzss),
             6 => Ok(GnssId::Glonass),
-            x => Err(format!("invalid GNSS ID: {}", x)),
+            x => Err(x),
+        }
+    }
+}
+
+impl fmt::Display for GnssId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            GnssId::Gps => "GPS",
+            GnssId::Sbas => "SBAS",
+            GnssId::Galileo => "Galileo",
+            GnssId::BeiDou => "BeiDou",
+            GnssId::Imes => "IMES",
+            GnssId::Qzss => "QZSS",
+            GnssId::Glonass => "GLONASS",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for GnssId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<GnssId, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "gps" => Ok(GnssId::Gps),
+            "sbas" => Ok(GnssId::Sbas),
+            "galileo" | "gal" => Ok(GnssId::Galileo),
+            "beidou" | "bds" => Ok(GnssId::BeiDou),
+            "imes" => Ok(GnssId::Imes),
+            "qzss" => Ok(GnssId::Qzss),
+            "glonass" | "glo" => Ok(GnssId::Glonass),
+            _ => Err(format!("unknown GNSS: {}", s)),
         }
     }
 }
@@ -1,15 +1,31 @@
+mod cfg_key;
 mod gnss_id;
+mod ubx_ack;
+mod ubx_cfg_cfg;
 mod ubx_cfg_gnss;
 mod ubx_cfg_msg;
 mod ubx_cfg_prt;
 mod ubx_cfg_rate;
+mod ubx_cfg_rst;
+mod ubx_cfg_val;
+mod ubx_mon_ver;
+mod ubx_nav_pvt;
+mod ubx_nav_sat;
 mod ubx_rxm_rawx;
 mod ubx_rxm_sfrbx;
 
+pub use cfg_key::*;
 pub use gnss_id::*;
+pub use ubx_ack::*;
+pub use ubx_cfg_cfg::*;
 pub use ubx_cfg_gnss::*;
 pub use ubx_cfg_msg::*;
 pub use ubx_cfg_prt::*;
 pub use ubx_cfg_rate::*;
+pub use ubx_cfg_rst::*;
+pub use ubx_cfg_val::*;
+pub use ubx_mon_ver::*;
+pub use ubx_nav_pvt::*;
+pub use ubx_nav_sat::*;
 pub use ubx_rxm_rawx::*;
 pub use ubx_rxm_sfrbx::*;
@@ -0,0 +1,71 @@
+use std::convert::TryFrom;
+
+use crate::ublox::UbxError;
+
+/// The receiver accepted the message with the given class and ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UbxAckAck {
+    pub class: u8,
+    pub id: u8,
+}
+
+/// The receiver rejected the message with the given class and ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UbxAckNak {
+    pub class: u8,
+    pub id: u8,
+}
+
+impl UbxAckAck {
+    pub const CLASS: u8 = 0x05;
+    pub const ID: u8 = 0x01;
+}
+
+impl UbxAckNak {
+    pub const CLASS: u8 = 0x05;
+    pub const ID: u8 = 0x00;
+}
+
+impl From<UbxAckAck> for Vec<u8> {
+    fn from(msg: UbxAckAck) -> Vec<u8> {
+        vec![msg.class, msg.id]
+    }
+}
+
+impl From<UbxAckNak> for Vec<u8> {
+    fn from(msg: UbxAckNak) -> Vec<u8> {
+        vec![msg.class, msg.id]
+    }
+}
+
+impl TryFrom<Vec<u8>> for UbxAckAck {
+    type Error = UbxError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, UbxError> {
+        match bytes[..] {
+            [class, id] => Ok(UbxAckAck { class, id }),
+            _ => Err(UbxError::WrongLength {
+                class: Self::CLASS,
+                id: Self::ID,
+                expected: 2,
+                actual: bytes.len(),
+            }),
+        }
+    }
+}
+
+impl TryFrom<Vec<u8>> for UbxAckNak {
+    type Error = UbxError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, UbxError> {
+        match bytes[..] {
+            [class, id] => Ok(UbxAckNak { class, id }),
+            _ => Err(UbxError::WrongLength {
+                class: Self::CLASS,
+                id: Self::ID,
+                expected: 2,
+                actual: bytes.len(),
+            }),
+        }
+    }
+}
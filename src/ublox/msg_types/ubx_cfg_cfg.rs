@@ -0,0 +1,164 @@
+use std::convert::TryFrom;
+
+use bitflags::bitflags;
+
+use crate::ublox::UbxError;
+
+bitflags! {
+    /// Sections of the configuration affected by CFG-CFG.
+    pub struct UbxCfgCfgMask: u32 {
+        const IO_PORT = 0x0001;
+        const MSG_CONF = 0x0002;
+        const INF_MSG = 0x0004;
+        const NAV_CONF = 0x0008;
+        const RXM_CONF = 0x0010;
+        const SEN_CONF = 0x0100;
+        const RINV_CONF = 0x0200;
+        const ANT_CONF = 0x0400;
+        const LOG_CONF = 0x0800;
+        const FTS_CONF = 0x1000;
+    }
+}
+
+bitflags! {
+    /// Permanent storage devices a CFG-CFG applies to.
+    pub struct UbxCfgCfgDevices: u8 {
+        const BBR = 0x01;
+        const FLASH = 0x02;
+        const EEPROM = 0x04;
+        const SPI_FLASH = 0x10;
+    }
+}
+
+/// Clears, saves or loads the permanent configuration. The actions are carried out in that
+/// order; without a device mask the receiver picks its default storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UbxCfgCfg {
+    pub clear_mask: UbxCfgCfgMask,
+    pub save_mask: UbxCfgCfgMask,
+    pub load_mask: UbxCfgCfgMask,
+    pub devices: Option<UbxCfgCfgDevices>,
+}
+
+impl UbxCfgCfg {
+    pub const CLASS: u8 = 0x06;
+    pub const ID: u8 = 0x09;
+
+    /// Saves the whole current configuration.
+    pub fn save(devices: Option<UbxCfgCfgDevices>) -> Self {
+        UbxCfgCfg {
+            clear_mask: UbxCfgCfgMask::empty(),
+            save_mask: UbxCfgCfgMask::all(),
+            load_mask: UbxCfgCfgMask::empty(),
+            devices,
+        }
+    }
+
+    /// Loads the permanent configuration into the current one.
+    pub fn load(devices: Option<UbxCfgCfgDevices>) -> Self {
+        UbxCfgCfg {
+            clear_mask: UbxCfgCfgMask::empty(),
+            save_mask: UbxCfgCfgMask::empty(),
+            load_mask: UbxCfgCfgMask::all(),
+            devices,
+        }
+    }
+
+    /// Clears the permanent configuration and loads it, which brings back the defaults.
+    pub fn revert(devices: Option<UbxCfgCfgDevices>) -> Self {
+        UbxCfgCfg {
+            clear_mask: UbxCfgCfgMask::all(),
+            save_mask: UbxCfgCfgMask::empty(),
+            load_mask: UbxCfgCfgMask::all(),
+            devices,
+        }
+    }
+}
+
+impl From<UbxCfgCfg> for Vec<u8> {
+    fn from(msg: UbxCfgCfg) -> Vec<u8> {
+        let mut result = Vec::with_capacity(13);
+        result.extend_from_slice(&msg.clear_mask.bits().to_le_bytes());
+        result.extend_from_slice(&msg.save_mask.bits().to_le_bytes());
+        result.extend_from_slice(&msg.load_mask.bits().to_le_bytes());
+        if let Some(devices) = msg.devices {
+            result.push(devices.bits());
+        }
+        result
+    }
+}
+
+impl TryFrom<Vec<u8>> for UbxCfgCfg {
+    type Error = UbxError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, UbxError> {
+        if bytes.len() != 12 && bytes.len() != 13 {
+            return Err(UbxError::UnexpectedLength {
+                class: Self::CLASS,
+                id: Self::ID,
+                actual: bytes.len(),
+            });
+        }
+        let mask = |offset: usize, field: &'static str| {
+            let bits = u32::from_le_bytes([
+                bytes[offset],
+                bytes[offset + 1],
+                bytes[offset + 2],
+                bytes[offset + 3],
+            ]);
+            UbxCfgCfgMask::from_bits(bits).ok_or(UbxError::InvalidField {
+                class: Self::CLASS,
+                id: Self::ID,
+                offset,
+                field,
+                value: bits,
+            })
+        };
+        let devices = match bytes.get(12) {
+            Some(&bits) => Some(UbxCfgCfgDevices::from_bits(bits).ok_or(
+                UbxError::InvalidField {
+                    class: Self::CLASS,
+                    id: Self::ID,
+                    offset: 12,
+                    field: "deviceMask",
+                    value: bits as u32,
+                },
+            )?),
+            None => None,
+        };
+        Ok(UbxCfgCfg {
+            clear_mask: mask(0, "clearMask")?,
+            save_mask: mask(4, "saveMask")?,
+            load_mask: mask(8, "loadMask")?,
+            devices,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let msg = UbxCfgCfg::save(None);
+        let bytes: Vec<u8> = msg.into();
+        assert_eq!(bytes, vec![0, 0, 0, 0, 0x1f, 0x1f, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(UbxCfgCfg::try_from(bytes).unwrap(), msg);
+
+        let msg = UbxCfgCfg::revert(Some(UbxCfgCfgDevices::BBR | UbxCfgCfgDevices::FLASH));
+        let bytes: Vec<u8> = msg.into();
+        assert_eq!(
+            bytes,
+            vec![0x1f, 0x1f, 0, 0, 0, 0, 0, 0, 0x1f, 0x1f, 0, 0, 0x03]
+        );
+        assert_eq!(UbxCfgCfg::try_from(bytes).unwrap(), msg);
+
+        let msg = UbxCfgCfg::load(Some(UbxCfgCfgDevices::SPI_FLASH));
+        let bytes: Vec<u8> = msg.into();
+        assert_eq!(&bytes[8..], &[0x1f, 0x1f, 0, 0, 0x10]);
+        assert_eq!(UbxCfgCfg::try_from(bytes).unwrap(), msg);
+
+        assert!(UbxCfgCfg::try_from(vec![0; 11]).is_err());
+    }
+}
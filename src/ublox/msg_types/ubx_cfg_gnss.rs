@@ -3,6 +3,7 @@ use std::convert::TryFrom;
 use bitflags::bitflags;
 
 use super::GnssId;
+use crate::ublox::UbxError;
 
 bitflags! {
     pub struct UbxCfgGnssBlockFlagsGps: u8 {
@@ -28,13 +29,19 @@ impl From<UbxCfgGnssBlockFlags> for Vec<u8> {
 }
 
 impl UbxCfgGnssBlockFlags {
-    fn gps_try_from(val: u8) -> Result<UbxCfgGnssBlockFlags, String> {
+    fn gps_try_from(val: u8) -> Result<UbxCfgGnssBlockFlags, UbxError> {
         UbxCfgGnssBlockFlagsGps::from_bits(val)
-            .ok_or_else(|| format!("invalid UbxCfgGnssBlockFlagsGps: {}", val))
+            .ok_or(UbxError::InvalidField {
+                class: UbxCfgGnss::CLASS,
+                id: UbxCfgGnss::ID,
+                offset: 6,
+                field: "sigCfgMask",
+                value: val as u32,
+            })
             .map(UbxCfgGnssBlockFlags::Gps)
     }
 
-    fn other_try_from(_val: u8) -> Result<UbxCfgGnssBlockFlags, String> {
+    fn other_try_from(_val: u8) -> Result<UbxCfgGnssBlockFlags, UbxError> {
         Ok(UbxCfgGnssBlockFlags::Other)
     }
 }
@@ -63,18 +70,30 @@ impl From<UbxCfgGnssBlock> for Vec<u8> {
     }
 }
 
+/// Offsets in errors are relative to the start of the block.
 impl TryFrom<Vec<u8>> for UbxCfgGnssBlock {
-    type Error = String;
-
-    fn try_from(bytes: Vec<u8>) -> Result<UbxCfgGnssBlock, String> {
-        let gnss_id = GnssId::try_from(bytes[0])?;
+    type Error = UbxError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<UbxCfgGnssBlock, UbxError> {
+        let gnss_id = GnssId::try_from(bytes[0]).map_err(|value| UbxError::UnknownGnssId {
+            class: UbxCfgGnss::CLASS,
+            id: UbxCfgGnss::ID,
+            offset: 0,
+            value,
+        })?;
         let res_trk_ch = bytes[1];
         let max_trk_ch = bytes[2];
         let enabled = match bytes[4] {
             0 => false,
             1 => true,
             x => {
-                return Err(format!("UbxCfgGnssBlock: invalid value for enabled: {}", x));
+                return Err(UbxError::InvalidField {
+                    class: UbxCfgGnss::CLASS,
+                    id: UbxCfgGnss::ID,
+                    offset: 4,
+                    field: "flags.enable",
+                    value: x as u32,
+                });
             }
         };
         let flags = match gnss_id {
@@ -103,6 +122,11 @@ pub enum UbxCfgGnss {
     },
 }
 
+impl UbxCfgGnss {
+    pub const CLASS: u8 = 0x06;
+    pub const ID: u8 = 0x3e;
+}
+
 impl From<UbxCfgGnss> for Vec<u8> {
     fn from(msg: UbxCfgGnss) -> Vec<u8> {
         match msg {
@@ -129,19 +153,28 @@ impl From<UbxCfgGnss> for Vec<u8> {
 }
 
 impl TryFrom<Vec<u8>> for UbxCfgGnss {
-    type Error = String;
+    type Error = UbxError;
 
-    fn try_from(bytes: Vec<u8>) -> Result<Self, String> {
+    fn try_from(bytes: Vec<u8>) -> Result<Self, UbxError> {
         match bytes.len() {
             0 => Ok(UbxCfgGnss::Poll),
-            x if (x - 4) % 8 == 0 => {
+            x if x >= 4 => {
                 let version = bytes[0];
                 let num_trk_ch_hw = bytes[1];
                 let num_trk_ch_use = bytes[2];
                 let num_blocks = bytes[3] as usize;
+                if x != 4 + 8 * num_blocks {
+                    return Err(UbxError::WrongLength {
+                        class: Self::CLASS,
+                        id: Self::ID,
+                        expected: 4 + 8 * num_blocks,
+                        actual: x,
+                    });
+                }
                 let mut config_blocks = vec![];
                 for i in 0..num_blocks {
-                    let block = UbxCfgGnssBlock::try_from(bytes[4 + 8 * i..12 + 8 * i].to_vec())?;
+                    let block = UbxCfgGnssBlock::try_from(bytes[4 + 8 * i..12 + 8 * i].to_vec())
+                        .map_err(|err| err.shifted(4 + 8 * i))?;
                     config_blocks.push(block);
                 }
                 Ok(UbxCfgGnss::Settings {
@@ -151,7 +184,12 @@ impl TryFrom<Vec<u8>> for UbxCfgGnss {
                     config_blocks,
                 })
             }
-            x => Err(format!("unexpected len for a UbxCfgGnss: {}", x)),
+            x => Err(UbxError::PayloadTooShort {
+                class: Self::CLASS,
+                id: Self::ID,
+                expected: 4,
+                actual: x,
+            }),
         }
     }
 }
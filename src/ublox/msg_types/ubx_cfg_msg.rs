@@ -1,5 +1,7 @@
 use std::convert::TryFrom;
 
+use crate::ublox::UbxError;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UbxCfgMsg {
     Get { class: u8, id: u8 },
@@ -7,6 +9,11 @@ pub enum UbxCfgMsg {
     SetRatePorts { class: u8, id: u8, rate: [u8; 6] },
 }
 
+impl UbxCfgMsg {
+    pub const CLASS: u8 = 0x06;
+    pub const ID: u8 = 0x01;
+}
+
 impl From<UbxCfgMsg> for Vec<u8> {
     fn from(msg: UbxCfgMsg) -> Vec<u8> {
         match msg {
@@ -22,9 +29,9 @@ impl From<UbxCfgMsg> for Vec<u8> {
 }
 
 impl TryFrom<Vec<u8>> for UbxCfgMsg {
-    type Error = String;
+    type Error = UbxError;
 
-    fn try_from(bytes: Vec<u8>) -> Result<Self, String> {
+    fn try_from(bytes: Vec<u8>) -> Result<Self, UbxError> {
         match bytes.len() {
             2 => Ok(UbxCfgMsg::Get {
                 class: bytes[0],
@@ -44,7 +51,11 @@ impl TryFrom<Vec<u8>> for UbxCfgMsg {
                     rate,
                 })
             }
-            x => Err(format!("unexpected len for a UbxCfgMsg: {}", x)),
+            x => Err(UbxError::UnexpectedLength {
+                class: Self::CLASS,
+                id: Self::ID,
+                actual: x,
+            }),
         }
     }
 }
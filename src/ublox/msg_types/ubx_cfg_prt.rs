@@ -2,8 +2,10 @@ use std::convert::TryFrom;
 
 use bitflags::bitflags;
 
+use crate::ublox::UbxError;
+
 bitflags! {
-    pub struct UbxCfgPrtUsbInMask: u16 {
+    pub struct UbxCfgPrtInMask: u16 {
         const UBX = 0x01;
         const NMEA = 0x02;
         const RTCM = 0x04;
@@ -12,46 +14,285 @@ bitflags! {
 }
 
 bitflags! {
-    pub struct UbxCfgPrtUsbOutMask: u16 {
+    pub struct UbxCfgPrtOutMask: u16 {
         const UBX = 0x01;
         const NMEA = 0x02;
         const RTCM3 = 0x20;
     }
 }
 
+bitflags! {
+    pub struct UbxCfgPrtFlags: u16 {
+        /// Extended TX timeout - the port keeps buffering output for up to 1.5 s when the host
+        /// doesn't read it.
+        const EXTENDED_TX_TIMEOUT = 0x02;
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum UbxCfgPrt {
-    Get {
+pub enum UbxCfgPrtDataBits {
+    Five = 0,
+    Six = 1,
+    Seven = 2,
+    Eight = 3,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UbxCfgPrtParity {
+    Even = 0,
+    Odd = 1,
+    None = 4,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UbxCfgPrtStopBits {
+    One = 0,
+    OneAndHalf = 1,
+    Two = 2,
+    Half = 3,
+}
+
+/// Port-specific part of the settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UbxCfgPrtPort {
+    /// The DDC (I2C compatible) port; `address` is the 7-bit slave address.
+    I2c {
+        address: u8,
+    },
+    /// UART1 or UART2, depending on `port_id`.
+    Uart {
         port_id: u8,
+        baud_rate: u32,
+        data_bits: UbxCfgPrtDataBits,
+        parity: UbxCfgPrtParity,
+        stop_bits: UbxCfgPrtStopBits,
     },
-    SetUsb {
-        in_mask: UbxCfgPrtUsbInMask,
-        out_mask: UbxCfgPrtUsbOutMask,
+    Usb,
+    Spi {
+        /// SPI mode 0-3 (clock polarity and phase).
+        mode: u8,
+        flow_control: bool,
+        /// Number of 0xff bytes, up to 63, after which the port is deactivated; 0 disables the
+        /// feature.
+        ff_count: u8,
     },
 }
 
+impl UbxCfgPrtPort {
+    pub fn port_id(&self) -> u8 {
+        match *self {
+            UbxCfgPrtPort::I2c { .. } => 0,
+            UbxCfgPrtPort::Uart { port_id, .. } => port_id,
+            UbxCfgPrtPort::Usb => 3,
+            UbxCfgPrtPort::Spi { .. } => 4,
+        }
+    }
+}
+
+/// The TX-ready signal, raised on a PIO when output data is waiting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct UbxCfgPrtTxReady {
+    pub enabled: bool,
+    pub active_low: bool,
+    pub pin: u8,
+    /// Amount of pending data that raises the signal, in bytes - rounded down to a multiple of 8.
+    pub threshold: u16,
+}
+
+impl UbxCfgPrtTxReady {
+    fn from_bits(bits: u16) -> Self {
+        UbxCfgPrtTxReady {
+            enabled: bits & 0x01 != 0,
+            active_low: bits & 0x02 != 0,
+            pin: ((bits >> 2) & 0x1f) as u8,
+            threshold: (bits >> 7) * 8,
+        }
+    }
+
+    fn bits(&self) -> u16 {
+        (self.enabled as u16)
+            | (self.active_low as u16) << 1
+            | (self.pin as u16 & 0x1f) << 2
+            | (self.threshold / 8).min(0x1ff) << 7
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UbxCfgPrtSettings {
+    pub port: UbxCfgPrtPort,
+    pub tx_ready: UbxCfgPrtTxReady,
+    pub in_mask: UbxCfgPrtInMask,
+    pub out_mask: UbxCfgPrtOutMask,
+    pub flags: UbxCfgPrtFlags,
+}
+
+impl UbxCfgPrtSettings {
+    pub fn usb(in_mask: UbxCfgPrtInMask, out_mask: UbxCfgPrtOutMask) -> Self {
+        UbxCfgPrtSettings {
+            port: UbxCfgPrtPort::Usb,
+            tx_ready: UbxCfgPrtTxReady::default(),
+            in_mask,
+            out_mask,
+            flags: UbxCfgPrtFlags::empty(),
+        }
+    }
+
+    /// UART1 or UART2 in 8N1 mode.
+    pub fn uart(
+        port_id: u8,
+        baud_rate: u32,
+        in_mask: UbxCfgPrtInMask,
+        out_mask: UbxCfgPrtOutMask,
+    ) -> Self {
+        UbxCfgPrtSettings {
+            port: UbxCfgPrtPort::Uart {
+                port_id,
+                baud_rate,
+                data_bits: UbxCfgPrtDataBits::Eight,
+                parity: UbxCfgPrtParity::None,
+                stop_bits: UbxCfgPrtStopBits::One,
+            },
+            ..Self::usb(in_mask, out_mask)
+        }
+    }
+
+    pub fn i2c(address: u8, in_mask: UbxCfgPrtInMask, out_mask: UbxCfgPrtOutMask) -> Self {
+        UbxCfgPrtSettings {
+            port: UbxCfgPrtPort::I2c { address },
+            ..Self::usb(in_mask, out_mask)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UbxCfgPrt {
+    Get { port_id: u8 },
+    Set(UbxCfgPrtSettings),
+}
+
+impl UbxCfgPrt {
+    pub const CLASS: u8 = 0x06;
+    pub const ID: u8 = 0x00;
+
+    fn invalid_field(offset: usize, field: &'static str, value: u32) -> UbxError {
+        UbxError::InvalidField {
+            class: Self::CLASS,
+            id: Self::ID,
+            offset,
+            field,
+            value,
+        }
+    }
+
+    fn decode_port(port_id: u8, mode: u32, baud_rate: u32) -> Result<UbxCfgPrtPort, UbxError> {
+        match port_id {
+            0 => Ok(UbxCfgPrtPort::I2c {
+                address: ((mode >> 1) & 0x7f) as u8,
+            }),
+            1 | 2 => {
+                let data_bits = match (mode >> 6) & 0x03 {
+                    0 => UbxCfgPrtDataBits::Five,
+                    1 => UbxCfgPrtDataBits::Six,
+                    2 => UbxCfgPrtDataBits::Seven,
+                    _ => UbxCfgPrtDataBits::Eight,
+                };
+                let parity = match (mode >> 9) & 0x07 {
+                    0 => UbxCfgPrtParity::Even,
+                    1 => UbxCfgPrtParity::Odd,
+                    4 | 5 => UbxCfgPrtParity::None,
+                    _ => return Err(Self::invalid_field(4, "mode", mode)),
+                };
+                let stop_bits = match (mode >> 12) & 0x03 {
+                    0 => UbxCfgPrtStopBits::One,
+                    1 => UbxCfgPrtStopBits::OneAndHalf,
+                    2 => UbxCfgPrtStopBits::Two,
+                    _ => UbxCfgPrtStopBits::Half,
+                };
+                Ok(UbxCfgPrtPort::Uart {
+                    port_id,
+                    baud_rate,
+                    data_bits,
+                    parity,
+                    stop_bits,
+                })
+            }
+            3 => Ok(UbxCfgPrtPort::Usb),
+            4 => Ok(UbxCfgPrtPort::Spi {
+                mode: ((mode >> 1) & 0x03) as u8,
+                flow_control: mode & 0x40 != 0,
+                ff_count: ((mode >> 8) & 0x3f) as u8,
+            }),
+            x => Err(Self::invalid_field(0, "portID", x as u32)),
+        }
+    }
+
+    fn encode_port(port: &UbxCfgPrtPort) -> (u32, u32) {
+        match *port {
+            UbxCfgPrtPort::I2c { address } => ((address as u32 & 0x7f) << 1, 0),
+            UbxCfgPrtPort::Uart {
+                baud_rate,
+                data_bits,
+                parity,
+                stop_bits,
+                ..
+            } => {
+                // bit 4 is reserved and has to be set for compatibility with u-blox 5
+                let mode = 0x10
+                    | (data_bits as u32) << 6
+                    | (parity as u32) << 9
+                    | (stop_bits as u32) << 12;
+                (mode, baud_rate)
+            }
+            UbxCfgPrtPort::Usb => (0, 0),
+            UbxCfgPrtPort::Spi {
+                mode,
+                flow_control,
+                ff_count,
+            } => {
+                let mode = (mode as u32 & 0x03) << 1
+                    | (flow_control as u32) << 6
+                    | (ff_count as u32 & 0x3f) << 8;
+                (mode, 0)
+            }
+        }
+    }
+}
+
 impl TryFrom<Vec<u8>> for UbxCfgPrt {
-    type Error = String;
+    type Error = UbxError;
 
-    fn try_from(bytes: Vec<u8>) -> Result<Self, String> {
+    fn try_from(bytes: Vec<u8>) -> Result<Self, UbxError> {
         match bytes.len() {
             1 => Ok(UbxCfgPrt::Get { port_id: bytes[0] }),
             20 => {
-                let port_id = bytes[0];
-                match port_id {
-                    3 => {
-                        let flags = u16::from_le_bytes([bytes[12], bytes[13]]);
-                        let in_mask = UbxCfgPrtUsbInMask::from_bits(flags)
-                            .ok_or_else(|| format!("invalid UbxCfgPrtInMask: {}", flags))?;
-                        let flags = u16::from_le_bytes([bytes[14], bytes[15]]);
-                        let out_mask = UbxCfgPrtUsbOutMask::from_bits(flags)
-                            .ok_or_else(|| format!("invalid UbxCfgPrtOutMask: {}", flags))?;
-                        Ok(UbxCfgPrt::SetUsb { in_mask, out_mask })
-                    }
-                    _ => unimplemented!(),
-                }
+                let tx_ready = u16::from_le_bytes([bytes[2], bytes[3]]);
+                let mode = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+                let baud_rate = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+                let port = Self::decode_port(bytes[0], mode, baud_rate)?;
+
+                let flags = u16::from_le_bytes([bytes[12], bytes[13]]);
+                let in_mask = UbxCfgPrtInMask::from_bits(flags)
+                    .ok_or_else(|| Self::invalid_field(12, "inProtoMask", flags as u32))?;
+                let flags = u16::from_le_bytes([bytes[14], bytes[15]]);
+                let out_mask = UbxCfgPrtOutMask::from_bits(flags)
+                    .ok_or_else(|| Self::invalid_field(14, "outProtoMask", flags as u32))?;
+                let flags = u16::from_le_bytes([bytes[16], bytes[17]]);
+                let flags = UbxCfgPrtFlags::from_bits(flags)
+                    .ok_or_else(|| Self::invalid_field(16, "flags", flags as u32))?;
+
+                Ok(UbxCfgPrt::Set(UbxCfgPrtSettings {
+                    port,
+                    tx_ready: UbxCfgPrtTxReady::from_bits(tx_ready),
+                    in_mask,
+                    out_mask,
+                    flags,
+                }))
             }
-            x => Err(format!("unexpected len for a UbxCfgPrt: {}", x)),
+            x => Err(UbxError::UnexpectedLength {
+                class: Self::CLASS,
+                id: Self::ID,
+                actual: x,
+            }),
         }
     }
 }
@@ -60,13 +301,80 @@ impl From<UbxCfgPrt> for Vec<u8> {
     fn from(msg: UbxCfgPrt) -> Vec<u8> {
         match msg {
             UbxCfgPrt::Get { port_id } => vec![port_id],
-            UbxCfgPrt::SetUsb { in_mask, out_mask } => {
-                let [in0, in1] = in_mask.bits().to_le_bytes();
-                let [out0, out1] = out_mask.bits().to_le_bytes();
-                vec![
-                    3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, in0, in1, out0, out1, 0, 0, 0, 0,
-                ]
+            UbxCfgPrt::Set(settings) => {
+                let (mode, baud_rate) = UbxCfgPrt::encode_port(&settings.port);
+                let mut result = vec![settings.port.port_id(), 0];
+                result.extend_from_slice(&settings.tx_ready.bits().to_le_bytes());
+                result.extend_from_slice(&mode.to_le_bytes());
+                result.extend_from_slice(&baud_rate.to_le_bytes());
+                result.extend_from_slice(&settings.in_mask.bits().to_le_bytes());
+                result.extend_from_slice(&settings.out_mask.bits().to_le_bytes());
+                result.extend_from_slice(&settings.flags.bits().to_le_bytes());
+                result.extend_from_slice(&[0, 0]);
+                result
             }
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn uart_round_trip() {
+        let msg = UbxCfgPrt::Set(UbxCfgPrtSettings::uart(
+            1,
+            115200,
+            UbxCfgPrtInMask::UBX | UbxCfgPrtInMask::NMEA,
+            UbxCfgPrtOutMask::UBX,
+        ));
+        let bytes: Vec<u8> = msg.into();
+        assert_eq!(
+            bytes,
+            vec![1, 0, 0, 0, 0xd0, 0x08, 0, 0, 0x00, 0xc2, 0x01, 0x00, 3, 0, 1, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(UbxCfgPrt::try_from(bytes).unwrap(), msg);
+    }
+
+    #[test]
+    fn other_ports_round_trip() {
+        let i2c = UbxCfgPrtSettings {
+            tx_ready: UbxCfgPrtTxReady {
+                enabled: true,
+                active_low: true,
+                pin: 6,
+                threshold: 64,
+            },
+            flags: UbxCfgPrtFlags::EXTENDED_TX_TIMEOUT,
+            ..UbxCfgPrtSettings::i2c(0x42, UbxCfgPrtInMask::UBX, UbxCfgPrtOutMask::UBX)
+        };
+        let spi = UbxCfgPrtSettings {
+            port: UbxCfgPrtPort::Spi {
+                mode: 3,
+                flow_control: true,
+                ff_count: 50,
+            },
+            ..UbxCfgPrtSettings::usb(UbxCfgPrtInMask::UBX, UbxCfgPrtOutMask::NMEA)
+        };
+        for &settings in &[i2c, spi] {
+            let bytes: Vec<u8> = UbxCfgPrt::Set(settings).into();
+            assert_eq!(bytes.len(), 20);
+            assert_eq!(
+                UbxCfgPrt::try_from(bytes).unwrap(),
+                UbxCfgPrt::Set(settings)
+            );
+        }
+
+        // the bits above the 6-bit ffCnt aren't part of it
+        let mut bytes: Vec<u8> = UbxCfgPrt::Set(spi).into();
+        bytes[5] |= 0xc0;
+        match UbxCfgPrt::try_from(bytes).unwrap() {
+            UbxCfgPrt::Set(UbxCfgPrtSettings {
+                port: UbxCfgPrtPort::Spi { ff_count, .. },
+                ..
+            }) => assert_eq!(ff_count, 50),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
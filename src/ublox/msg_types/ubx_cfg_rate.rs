@@ -1,5 +1,7 @@
 use std::convert::TryFrom;
 
+use crate::ublox::UbxError;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UbxCfgRateTimeRef {
     Utc = 0,
@@ -10,53 +12,82 @@ pub enum UbxCfgRateTimeRef {
 }
 
 impl TryFrom<u16> for UbxCfgRateTimeRef {
-    type Error = String;
+    type Error = u16;
 
-    fn try_from(val: u16) -> Result<Self, String> {
+    fn try_from(val: u16) -> Result<Self, u16> {
         match val {
             0 => Ok(UbxCfgRateTimeRef::Utc),
             1 => Ok(UbxCfgRateTimeRef::Gps),
             2 => Ok(UbxCfgRateTimeRef::Glonass),
             3 => Ok(UbxCfgRateTimeRef::BeiDou),
             4 => Ok(UbxCfgRateTimeRef::Galileo),
-            x => Err(format!("unexpected value for UbxCfgRateTimeRef: {}", x)),
+            x => Err(x),
         }
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct UbxCfgRate {
+pub struct UbxCfgRateSettings {
     pub meas_rate_ms: u16,
     pub nav_rate_cycles: u16,
     pub time_ref: UbxCfgRateTimeRef,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UbxCfgRate {
+    Poll,
+    Set(UbxCfgRateSettings),
+}
+
+impl UbxCfgRate {
+    pub const CLASS: u8 = 0x06;
+    pub const ID: u8 = 0x08;
+}
+
 impl From<UbxCfgRate> for Vec<u8> {
     fn from(msg: UbxCfgRate) -> Vec<u8> {
-        let [mr0, mr1] = msg.meas_rate_ms.to_le_bytes();
-        let [nr0, nr1] = msg.nav_rate_cycles.to_le_bytes();
-        let [tr0, tr1] = (msg.time_ref as u16).to_le_bytes();
-        vec![mr0, mr1, nr0, nr1, tr0, tr1]
+        match msg {
+            UbxCfgRate::Poll => vec![],
+            UbxCfgRate::Set(settings) => {
+                let [mr0, mr1] = settings.meas_rate_ms.to_le_bytes();
+                let [nr0, nr1] = settings.nav_rate_cycles.to_le_bytes();
+                let [tr0, tr1] = (settings.time_ref as u16).to_le_bytes();
+                vec![mr0, mr1, nr0, nr1, tr0, tr1]
+            }
+        }
     }
 }
 
 impl TryFrom<Vec<u8>> for UbxCfgRate {
-    type Error = String;
+    type Error = UbxError;
 
-    fn try_from(bytes: Vec<u8>) -> Result<Self, String> {
+    fn try_from(bytes: Vec<u8>) -> Result<Self, UbxError> {
         match bytes.len() {
+            0 => Ok(UbxCfgRate::Poll),
             6 => {
                 let meas_rate_ms = u16::from_le_bytes([bytes[0], bytes[1]]);
                 let nav_rate_cycles = u16::from_le_bytes([bytes[2], bytes[3]]);
                 let time_ref_u16 = u16::from_le_bytes([bytes[4], bytes[5]]);
-                let time_ref = UbxCfgRateTimeRef::try_from(time_ref_u16)?;
-                Ok(UbxCfgRate {
+                let time_ref = UbxCfgRateTimeRef::try_from(time_ref_u16).map_err(|value| {
+                    UbxError::InvalidField {
+                        class: Self::CLASS,
+                        id: Self::ID,
+                        offset: 4,
+                        field: "timeRef",
+                        value: value as u32,
+                    }
+                })?;
+                Ok(UbxCfgRate::Set(UbxCfgRateSettings {
                     meas_rate_ms,
                     nav_rate_cycles,
                     time_ref,
-                })
+                }))
             }
-            x => Err(format!("unexpected len for a UbxCfgRate: {}", x)),
+            x => Err(UbxError::UnexpectedLength {
+                class: Self::CLASS,
+                id: Self::ID,
+                actual: x,
+            }),
         }
     }
 }
@@ -0,0 +1,167 @@
+use std::{convert::TryFrom, str::FromStr};
+
+use bitflags::bitflags;
+
+use crate::ublox::UbxError;
+
+bitflags! {
+    /// Parts of the battery-backed navigation data to clear on reset.
+    pub struct UbxCfgRstNavBbr: u16 {
+        const EPHEMERIS = 0x0001;
+        const ALMANAC = 0x0002;
+        const HEALTH = 0x0004;
+        const KLOBUCHAR = 0x0008;
+        const POSITION = 0x0010;
+        const CLOCK_DRIFT = 0x0020;
+        const OSCILLATOR = 0x0040;
+        const UTC = 0x0080;
+        const RTC = 0x0100;
+        const AOP = 0x8000;
+        /// Everything, including reserved bits - a cold start.
+        const COLD = 0xffff;
+    }
+}
+
+/// The kind of start after a reset, which decides how much navigation data survives it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UbxCfgRstStart {
+    Hot,
+    Warm,
+    Cold,
+}
+
+impl UbxCfgRstStart {
+    pub fn nav_bbr_mask(self) -> UbxCfgRstNavBbr {
+        match self {
+            UbxCfgRstStart::Hot => UbxCfgRstNavBbr::empty(),
+            UbxCfgRstStart::Warm => UbxCfgRstNavBbr::EPHEMERIS,
+            UbxCfgRstStart::Cold => UbxCfgRstNavBbr::COLD,
+        }
+    }
+}
+
+impl FromStr for UbxCfgRstStart {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<UbxCfgRstStart, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "hot" => Ok(UbxCfgRstStart::Hot),
+            "warm" => Ok(UbxCfgRstStart::Warm),
+            "cold" => Ok(UbxCfgRstStart::Cold),
+            _ => Err(format!("unknown start type: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UbxCfgRstMode {
+    /// Immediate hardware reset by the watchdog.
+    Hardware = 0x00,
+    /// Controlled software reset.
+    Software = 0x01,
+    /// Restarts only the GNSS tasks, without resetting the CPU.
+    GnssOnly = 0x02,
+    /// Hardware reset after shutting down the tasks.
+    HardwareAfterShutdown = 0x04,
+    GnssStop = 0x08,
+    GnssStart = 0x09,
+}
+
+impl TryFrom<u8> for UbxCfgRstMode {
+    type Error = u8;
+
+    fn try_from(val: u8) -> Result<Self, u8> {
+        match val {
+            0x00 => Ok(UbxCfgRstMode::Hardware),
+            0x01 => Ok(UbxCfgRstMode::Software),
+            0x02 => Ok(UbxCfgRstMode::GnssOnly),
+            0x04 => Ok(UbxCfgRstMode::HardwareAfterShutdown),
+            0x08 => Ok(UbxCfgRstMode::GnssStop),
+            0x09 => Ok(UbxCfgRstMode::GnssStart),
+            x => Err(x),
+        }
+    }
+}
+
+impl FromStr for UbxCfgRstMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<UbxCfgRstMode, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "hardware" | "hw" => Ok(UbxCfgRstMode::Hardware),
+            "software" | "sw" => Ok(UbxCfgRstMode::Software),
+            "gnss" => Ok(UbxCfgRstMode::GnssOnly),
+            _ => Err(format!("unknown reset mode: {}", s)),
+        }
+    }
+}
+
+/// Resets the receiver. The receiver doesn't acknowledge this message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UbxCfgRst {
+    pub nav_bbr_mask: UbxCfgRstNavBbr,
+    pub reset_mode: UbxCfgRstMode,
+}
+
+impl UbxCfgRst {
+    pub const CLASS: u8 = 0x06;
+    pub const ID: u8 = 0x04;
+
+    pub fn new(start: UbxCfgRstStart, reset_mode: UbxCfgRstMode) -> Self {
+        UbxCfgRst {
+            nav_bbr_mask: start.nav_bbr_mask(),
+            reset_mode,
+        }
+    }
+}
+
+impl From<UbxCfgRst> for Vec<u8> {
+    fn from(msg: UbxCfgRst) -> Vec<u8> {
+        let [m0, m1] = msg.nav_bbr_mask.bits().to_le_bytes();
+        vec![m0, m1, msg.reset_mode as u8, 0]
+    }
+}
+
+impl TryFrom<Vec<u8>> for UbxCfgRst {
+    type Error = UbxError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, UbxError> {
+        match bytes.len() {
+            4 => {
+                let nav_bbr_mask =
+                    UbxCfgRstNavBbr::from_bits_truncate(u16::from_le_bytes([bytes[0], bytes[1]]));
+                let reset_mode =
+                    UbxCfgRstMode::try_from(bytes[2]).map_err(|value| UbxError::InvalidField {
+                        class: Self::CLASS,
+                        id: Self::ID,
+                        offset: 2,
+                        field: "resetMode",
+                        value: value as u32,
+                    })?;
+                Ok(UbxCfgRst {
+                    nav_bbr_mask,
+                    reset_mode,
+                })
+            }
+            x => Err(UbxError::WrongLength {
+                class: Self::CLASS,
+                id: Self::ID,
+                expected: 4,
+                actual: x,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cold_start() {
+        let msg = UbxCfgRst::new(UbxCfgRstStart::Cold, UbxCfgRstMode::GnssOnly);
+        let bytes: Vec<u8> = msg.into();
+        assert_eq!(bytes, vec![0xff, 0xff, 0x02, 0x00]);
+        assert_eq!(UbxCfgRst::try_from(bytes).unwrap(), msg);
+    }
+}
@@ -0,0 +1,469 @@
+use std::{
+    convert::{TryFrom, TryInto},
+    str::FromStr,
+};
+
+use bitflags::bitflags;
+
+use super::{CfgItem, CfgKey};
+use crate::ublox::UbxError;
+
+/// The most items a single CFG-VALSET, CFG-VALGET or CFG-VALDEL message can carry.
+pub const MAX_CFG_ITEMS: usize = 64;
+
+bitflags! {
+    /// Where CFG-VALSET and CFG-VALDEL apply the configuration.
+    pub struct CfgLayers: u8 {
+        const RAM = 0x01;
+        const BBR = 0x02;
+        const FLASH = 0x04;
+    }
+}
+
+/// The single layer CFG-VALGET reads from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CfgLayer {
+    Ram = 0,
+    Bbr = 1,
+    Flash = 2,
+    Default = 7,
+}
+
+impl TryFrom<u8> for CfgLayer {
+    type Error = u8;
+
+    fn try_from(val: u8) -> Result<Self, u8> {
+        match val {
+            0 => Ok(CfgLayer::Ram),
+            1 => Ok(CfgLayer::Bbr),
+            2 => Ok(CfgLayer::Flash),
+            7 => Ok(CfgLayer::Default),
+            x => Err(x),
+        }
+    }
+}
+
+impl FromStr for CfgLayer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<CfgLayer, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "ram" => Ok(CfgLayer::Ram),
+            "bbr" => Ok(CfgLayer::Bbr),
+            "flash" => Ok(CfgLayer::Flash),
+            "default" => Ok(CfgLayer::Default),
+            _ => Err(format!("unknown configuration layer: {}", s)),
+        }
+    }
+}
+
+/// Lets a configuration span several messages and be applied at once, at the end. Messages
+/// outside of a transaction are applied right away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CfgTransaction {
+    None = 0,
+    Begin = 1,
+    Continue = 2,
+    Apply = 3,
+}
+
+impl TryFrom<u8> for CfgTransaction {
+    type Error = u8;
+
+    fn try_from(val: u8) -> Result<Self, u8> {
+        match val {
+            0 => Ok(CfgTransaction::None),
+            1 => Ok(CfgTransaction::Begin),
+            2 => Ok(CfgTransaction::Continue),
+            3 => Ok(CfgTransaction::Apply),
+            x => Err(x),
+        }
+    }
+}
+
+/// Items whose key has no value size, like wildcards, can't be encoded; they are left out rather
+/// than making the receiver misread the items after them.
+fn encode_items(items: Vec<CfgItem>) -> Vec<u8> {
+    let mut result = vec![];
+    for item in items {
+        let size = match item.key.size() {
+            Some(size) => size,
+            None => {
+                debug_assert!(false, "no value size for key {:#010x}", item.key.0);
+                continue;
+            }
+        };
+        result.extend(&item.key.0.to_le_bytes()[..]);
+        result.extend(&item.value.to_le_bytes()[..size]);
+    }
+    result
+}
+
+/// Decodes key/value pairs starting at `offset`.
+fn parse_items(bytes: &[u8], offset: usize, class: u8, id: u8) -> Result<Vec<CfgItem>, UbxError> {
+    let mut items = vec![];
+    let mut pos = offset;
+    while pos < bytes.len() {
+        if pos + 4 > bytes.len() {
+            return Err(UbxError::WrongLength {
+                class,
+                id,
+                expected: pos + 4,
+                actual: bytes.len(),
+            });
+        }
+        let key = CfgKey(u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap()));
+        let size = key.size().ok_or(UbxError::InvalidField {
+            class,
+            id,
+            offset: pos,
+            field: "keyID",
+            value: key.0,
+        })?;
+        pos += 4;
+        if pos + size > bytes.len() {
+            return Err(UbxError::WrongLength {
+                class,
+                id,
+                expected: pos + size,
+                actual: bytes.len(),
+            });
+        }
+        let mut value = [0; 8];
+        value[..size].copy_from_slice(&bytes[pos..pos + size]);
+        items.push(CfgItem::new(key, u64::from_le_bytes(value)));
+        pos += size;
+    }
+    Ok(items)
+}
+
+fn parse_keys(bytes: &[u8], offset: usize, class: u8, id: u8) -> Result<Vec<CfgKey>, UbxError> {
+    if (bytes.len() - offset) % 4 != 0 {
+        return Err(UbxError::WrongLength {
+            class,
+            id,
+            expected: bytes.len() - (bytes.len() - offset) % 4,
+            actual: bytes.len(),
+        });
+    }
+    Ok(bytes[offset..]
+        .chunks(4)
+        .map(|key| CfgKey(u32::from_le_bytes(key.try_into().unwrap())))
+        .collect())
+}
+
+/// Version 0 messages have no transaction; version 1 has it in the third byte.
+fn parse_transaction(bytes: &[u8], class: u8, id: u8) -> Result<CfgTransaction, UbxError> {
+    match bytes[0] {
+        0 => Ok(CfgTransaction::None),
+        1 => CfgTransaction::try_from(bytes[2] & 0x03).map_err(|value| UbxError::InvalidField {
+            class,
+            id,
+            offset: 2,
+            field: "transaction",
+            value: value as u32,
+        }),
+        x => Err(UbxError::InvalidField {
+            class,
+            id,
+            offset: 0,
+            field: "version",
+            value: x as u32,
+        }),
+    }
+}
+
+fn parse_layers(bytes: &[u8], class: u8, id: u8) -> Result<CfgLayers, UbxError> {
+    CfgLayers::from_bits(bytes[1]).ok_or(UbxError::InvalidField {
+        class,
+        id,
+        offset: 1,
+        field: "layers",
+        value: bytes[1] as u32,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UbxCfgValSet {
+    pub layers: CfgLayers,
+    pub transaction: CfgTransaction,
+    pub items: Vec<CfgItem>,
+}
+
+impl UbxCfgValSet {
+    pub const CLASS: u8 = 0x06;
+    pub const ID: u8 = 0x8a;
+
+    /// Splits the items into as many messages as needed, wrapped in a transaction if there is
+    /// more than one.
+    pub fn messages(layers: CfgLayers, items: Vec<CfgItem>) -> Vec<UbxCfgValSet> {
+        let chunks: Vec<_> = items.chunks(MAX_CFG_ITEMS).map(|c| c.to_vec()).collect();
+        let num_chunks = chunks.len();
+        chunks
+            .into_iter()
+            .enumerate()
+            .map(|(i, items)| UbxCfgValSet {
+                layers,
+                transaction: match i {
+                    _ if num_chunks == 1 => CfgTransaction::None,
+                    0 => CfgTransaction::Begin,
+                    i if i == num_chunks - 1 => CfgTransaction::Apply,
+                    _ => CfgTransaction::Continue,
+                },
+                items,
+            })
+            .collect()
+    }
+}
+
+impl From<UbxCfgValSet> for Vec<u8> {
+    fn from(msg: UbxCfgValSet) -> Vec<u8> {
+        let version = match msg.transaction {
+            CfgTransaction::None => 0,
+            _ => 1,
+        };
+        let mut result = vec![version, msg.layers.bits(), msg.transaction as u8, 0];
+        result.extend(encode_items(msg.items));
+        result
+    }
+}
+
+impl TryFrom<Vec<u8>> for UbxCfgValSet {
+    type Error = UbxError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, UbxError> {
+        if bytes.len() < 4 {
+            return Err(UbxError::PayloadTooShort {
+                class: Self::CLASS,
+                id: Self::ID,
+                expected: 4,
+                actual: bytes.len(),
+            });
+        }
+        Ok(UbxCfgValSet {
+            layers: parse_layers(&bytes, Self::CLASS, Self::ID)?,
+            transaction: parse_transaction(&bytes, Self::CLASS, Self::ID)?,
+            items: parse_items(&bytes, 4, Self::CLASS, Self::ID)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UbxCfgValGet {
+    /// Asks for the values of the given keys, which may be wildcards. Only `MAX_CFG_ITEMS`
+    /// values are returned at once; `position` skips that many of the matching items.
+    Request {
+        layer: CfgLayer,
+        position: u16,
+        keys: Vec<CfgKey>,
+    },
+    Response {
+        layer: CfgLayer,
+        position: u16,
+        items: Vec<CfgItem>,
+    },
+}
+
+impl UbxCfgValGet {
+    pub const CLASS: u8 = 0x06;
+    pub const ID: u8 = 0x8b;
+}
+
+impl From<UbxCfgValGet> for Vec<u8> {
+    fn from(msg: UbxCfgValGet) -> Vec<u8> {
+        let (version, layer, position, data) = match msg {
+            UbxCfgValGet::Request {
+                layer,
+                position,
+                keys,
+            } => {
+                let keys = keys.iter().flat_map(|key| key.0.to_le_bytes()).collect();
+                (0, layer, position, keys)
+            }
+            UbxCfgValGet::Response {
+                layer,
+                position,
+                items,
+            } => (1, layer, position, encode_items(items)),
+        };
+        let [p0, p1] = position.to_le_bytes();
+        let mut result = vec![version, layer as u8, p0, p1];
+        result.extend(data);
+        result
+    }
+}
+
+impl TryFrom<Vec<u8>> for UbxCfgValGet {
+    type Error = UbxError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, UbxError> {
+        if bytes.len() < 4 {
+            return Err(UbxError::PayloadTooShort {
+                class: Self::CLASS,
+                id: Self::ID,
+                expected: 4,
+                actual: bytes.len(),
+            });
+        }
+        let layer = CfgLayer::try_from(bytes[1]).map_err(|value| UbxError::InvalidField {
+            class: Self::CLASS,
+            id: Self::ID,
+            offset: 1,
+            field: "layer",
+            value: value as u32,
+        })?;
+        let position = u16::from_le_bytes([bytes[2], bytes[3]]);
+        match bytes[0] {
+            0 => Ok(UbxCfgValGet::Request {
+                layer,
+                position,
+                keys: parse_keys(&bytes, 4, Self::CLASS, Self::ID)?,
+            }),
+            1 => Ok(UbxCfgValGet::Response {
+                layer,
+                position,
+                items: parse_items(&bytes, 4, Self::CLASS, Self::ID)?,
+            }),
+            x => Err(UbxError::InvalidField {
+                class: Self::CLASS,
+                id: Self::ID,
+                offset: 0,
+                field: "version",
+                value: x as u32,
+            }),
+        }
+    }
+}
+
+/// Deletes items from the BBR and Flash layers, so that the defaults apply again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UbxCfgValDel {
+    pub layers: CfgLayers,
+    pub transaction: CfgTransaction,
+    pub keys: Vec<CfgKey>,
+}
+
+impl UbxCfgValDel {
+    pub const CLASS: u8 = 0x06;
+    pub const ID: u8 = 0x8c;
+}
+
+impl From<UbxCfgValDel> for Vec<u8> {
+    fn from(msg: UbxCfgValDel) -> Vec<u8> {
+        let version = match msg.transaction {
+            CfgTransaction::None => 0,
+            _ => 1,
+        };
+        let mut result = vec![version, msg.layers.bits(), msg.transaction as u8, 0];
+        result.extend(msg.keys.iter().flat_map(|key| key.0.to_le_bytes()));
+        result
+    }
+}
+
+impl TryFrom<Vec<u8>> for UbxCfgValDel {
+    type Error = UbxError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, UbxError> {
+        if bytes.len() < 4 {
+            return Err(UbxError::PayloadTooShort {
+                class: Self::CLASS,
+                id: Self::ID,
+                expected: 4,
+                actual: bytes.len(),
+            });
+        }
+        Ok(UbxCfgValDel {
+            layers: parse_layers(&bytes, Self::CLASS, Self::ID)?,
+            transaction: parse_transaction(&bytes, Self::CLASS, Self::ID)?,
+            keys: parse_keys(&bytes, 4, Self::CLASS, Self::ID)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn valset_round_trip() {
+        let items = vec![
+            CfgItem::new(CfgKey::RATE_MEAS, 200),
+            CfgItem::bool(CfgKey::SIGNAL_GLO_ENA, false),
+            CfgItem::new(CfgKey::UART1_BAUDRATE, 115200),
+        ];
+        let msg = UbxCfgValSet {
+            layers: CfgLayers::RAM | CfgLayers::BBR,
+            transaction: CfgTransaction::None,
+            items,
+        };
+        let bytes = Vec::<u8>::from(msg.clone());
+        assert_eq!(
+            bytes,
+            vec![
+                0x00, 0x03, 0x00, 0x00, 0x01, 0x00, 0x21, 0x30, 0xc8, 0x00, 0x25, 0x00, 0x31, 0x10,
+                0x00, 0x01, 0x00, 0x52, 0x40, 0x00, 0xc2, 0x01, 0x00
+            ]
+        );
+        assert_eq!(UbxCfgValSet::try_from(bytes).unwrap(), msg);
+    }
+
+    #[test]
+    fn valget_valdel_round_trip() {
+        let request = UbxCfgValGet::Request {
+            layer: CfgLayer::Flash,
+            position: 64,
+            keys: vec![CfgKey::ALL, CfgKey::RATE_MEAS],
+        };
+        let bytes = Vec::<u8>::from(request.clone());
+        assert_eq!(
+            bytes,
+            vec![0x00, 0x02, 0x40, 0x00, 0xff, 0xff, 0xff, 0x0f, 0x01, 0x00, 0x21, 0x30]
+        );
+        assert_eq!(UbxCfgValGet::try_from(bytes).unwrap(), request);
+
+        let response = UbxCfgValGet::Response {
+            layer: CfgLayer::Ram,
+            position: 0,
+            items: vec![
+                CfgItem::new(CfgKey::RATE_MEAS, 1000),
+                CfgItem::bool(CfgKey::SIGNAL_GLO_ENA, true),
+            ],
+        };
+        let bytes = Vec::<u8>::from(response.clone());
+        assert_eq!(
+            bytes,
+            vec![
+                0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x21, 0x30, 0xe8, 0x03, 0x25, 0x00, 0x31, 0x10,
+                0x01
+            ]
+        );
+        assert_eq!(UbxCfgValGet::try_from(bytes).unwrap(), response);
+        assert!(UbxCfgValGet::try_from(vec![0x00, 0x00, 0x00, 0x00, 0x01, 0x00]).is_err());
+
+        let del = UbxCfgValDel {
+            layers: CfgLayers::BBR | CfgLayers::FLASH,
+            transaction: CfgTransaction::Begin,
+            keys: vec![CfgKey::UART1_BAUDRATE],
+        };
+        let bytes = Vec::<u8>::from(del.clone());
+        assert_eq!(bytes, vec![0x01, 0x06, 0x01, 0x00, 0x01, 0x00, 0x52, 0x40]);
+        assert_eq!(UbxCfgValDel::try_from(bytes).unwrap(), del);
+    }
+
+    #[test]
+    fn valset_transaction() {
+        let items = vec![CfgItem::bool(CfgKey::SIGNAL_GPS_ENA, true); 150];
+        let transactions: Vec<_> = UbxCfgValSet::messages(CfgLayers::RAM, items)
+            .iter()
+            .map(|msg| msg.transaction)
+            .collect();
+        assert_eq!(
+            transactions,
+            vec![
+                CfgTransaction::Begin,
+                CfgTransaction::Continue,
+                CfgTransaction::Apply
+            ]
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use std::convert::TryFrom;
+
+use crate::ublox::UbxError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UbxMonVer {
+    Poll,
+    Version {
+        sw_version: String,
+        hw_version: String,
+        /// Extended information like "PROTVER=18.00" or "GPS;GLO;GAL;BDS".
+        extensions: Vec<String>,
+    },
+}
+
+impl UbxMonVer {
+    pub const CLASS: u8 = 0x0a;
+    pub const ID: u8 = 0x04;
+}
+
+/// A NUL-padded string field of the given length.
+fn str_field(s: &str, length: usize) -> Vec<u8> {
+    let mut result: Vec<u8> = s.bytes().take(length - 1).collect();
+    result.resize(length, 0);
+    result
+}
+
+fn parse_str_field(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|c| *c == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+impl From<UbxMonVer> for Vec<u8> {
+    fn from(msg: UbxMonVer) -> Vec<u8> {
+        match msg {
+            UbxMonVer::Poll => vec![],
+            UbxMonVer::Version {
+                sw_version,
+                hw_version,
+                extensions,
+            } => {
+                let mut result = str_field(&sw_version, 30);
+                result.extend(str_field(&hw_version, 10));
+                for extension in extensions {
+                    result.extend(str_field(&extension, 30));
+                }
+                result
+            }
+        }
+    }
+}
+
+impl TryFrom<Vec<u8>> for UbxMonVer {
+    type Error = UbxError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, UbxError> {
+        match bytes.len() {
+            0 => Ok(UbxMonVer::Poll),
+            x if x >= 40 && (x - 40) % 30 == 0 => Ok(UbxMonVer::Version {
+                sw_version: parse_str_field(&bytes[0..30]),
+                hw_version: parse_str_field(&bytes[30..40]),
+                extensions: bytes[40..].chunks(30).map(parse_str_field).collect(),
+            }),
+            x if x >= 40 => Err(UbxError::WrongLength {
+                class: Self::CLASS,
+                id: Self::ID,
+                expected: 40 + (x - 40) / 30 * 30,
+                actual: x,
+            }),
+            x => Err(UbxError::PayloadTooShort {
+                class: Self::CLASS,
+                id: Self::ID,
+                expected: 40,
+                actual: x,
+            }),
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use std::convert::{TryFrom, TryInto};
+
+use bitflags::bitflags;
+
+use crate::ublox::UbxError;
+
+bitflags! {
+    pub struct UbxNavPvtValid: u8 {
+        const VALID_DATE = 0x01;
+        const VALID_TIME = 0x02;
+        const FULLY_RESOLVED = 0x04;
+        const VALID_MAG = 0x08;
+    }
+}
+
+bitflags! {
+    pub struct UbxNavPvtFlags: u8 {
+        const GNSS_FIX_OK = 0x01;
+        const DIFF_SOLN = 0x02;
+        const HEAD_VEH_VALID = 0x20;
+        const CARR_SOLN_FLOAT = 0x40;
+        const CARR_SOLN_FIXED = 0x80;
+    }
+}
+
+bitflags! {
+    pub struct UbxNavPvtFlags2: u8 {
+        const CONFIRMED_AVAI = 0x20;
+        const CONFIRMED_DATE = 0x40;
+        const CONFIRMED_TIME = 0x80;
+    }
+}
+
+bitflags! {
+    pub struct UbxNavPvtFlags3: u8 {
+        const INVALID_LLH = 0x01;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UbxNavPvtFixType {
+    NoFix = 0,
+    DeadReckoning = 1,
+    Fix2D = 2,
+    Fix3D = 3,
+    GnssDeadReckoning = 4,
+    TimeOnly = 5,
+}
+
+impl TryFrom<u8> for UbxNavPvtFixType {
+    type Error = u8;
+
+    fn try_from(val: u8) -> Result<Self, u8> {
+        match val {
+            0 => Ok(UbxNavPvtFixType::NoFix),
+            1 => Ok(UbxNavPvtFixType::DeadReckoning),
+            2 => Ok(UbxNavPvtFixType::Fix2D),
+            3 => Ok(UbxNavPvtFixType::Fix3D),
+            4 => Ok(UbxNavPvtFixType::GnssDeadReckoning),
+            5 => Ok(UbxNavPvtFixType::TimeOnly),
+            x => Err(x),
+        }
+    }
+}
+
+/// Navigation solution: position, velocity and time. Angles are in degrees, distances in meters
+/// and velocities in m/s.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UbxNavPvt {
+    /// GPS time of week of the navigation epoch, in ms.
+    pub itow: u32,
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub min: u8,
+    pub sec: u8,
+    pub valid: UbxNavPvtValid,
+    /// Time accuracy estimate, in ns.
+    pub time_acc: u32,
+    /// Fraction of the second, in ns - can be negative.
+    pub nano: i32,
+    pub fix_type: UbxNavPvtFixType,
+    pub flags: UbxNavPvtFlags,
+    /// Power save mode state, bits 2-4 of the flags.
+    pub psm_state: u8,
+    pub flags2: UbxNavPvtFlags2,
+    pub num_sv: u8,
+    pub lon: f64,
+    pub lat: f64,
+    /// Height above the ellipsoid.
+    pub height: f64,
+    /// Height above mean sea level.
+    pub height_msl: f64,
+    pub horizontal_acc: f64,
+    pub vertical_acc: f64,
+    pub vel_north: f64,
+    pub vel_east: f64,
+    pub vel_down: f64,
+    pub ground_speed: f64,
+    pub heading_motion: f64,
+    pub speed_acc: f64,
+    pub heading_acc: f64,
+    pub pdop: f64,
+    pub flags3: UbxNavPvtFlags3,
+    /// Age of the most recent differential correction as the index of an age range, 0 if there's
+    /// none; bits 1-4 of flags3.
+    pub last_correction_age: u8,
+    pub heading_vehicle: f64,
+    pub mag_dec: f64,
+    pub mag_acc: f64,
+}
+
+impl UbxNavPvt {
+    pub const CLASS: u8 = 0x01;
+    pub const ID: u8 = 0x07;
+
+    /// Whether the message holds a valid position fix.
+    pub fn has_fix(&self) -> bool {
+        self.flags.contains(UbxNavPvtFlags::GNSS_FIX_OK)
+            && !self.flags3.contains(UbxNavPvtFlags3::INVALID_LLH)
+            && matches!(
+                self.fix_type,
+                UbxNavPvtFixType::Fix2D
+                    | UbxNavPvtFixType::Fix3D
+                    | UbxNavPvtFixType::GnssDeadReckoning
+            )
+    }
+
+    /// Whether the UTC date and time fields are valid.
+    pub fn utc_valid(&self) -> bool {
+        self.valid
+            .contains(UbxNavPvtValid::VALID_DATE | UbxNavPvtValid::VALID_TIME)
+    }
+}
+
+fn scaled_i32(bytes: &[u8], scale: f64) -> f64 {
+    i32::from_le_bytes(bytes.try_into().unwrap()) as f64 * scale
+}
+
+fn scaled_u32(bytes: &[u8], scale: f64) -> f64 {
+    u32::from_le_bytes(bytes.try_into().unwrap()) as f64 * scale
+}
+
+impl From<UbxNavPvt> for Vec<u8> {
+    fn from(msg: UbxNavPvt) -> Vec<u8> {
+        let i32_bytes = |val: f64, scale: f64| ((val / scale).round() as i32).to_le_bytes();
+        let u32_bytes = |val: f64, scale: f64| ((val / scale).round() as u32).to_le_bytes();
+        let mut result = vec![];
+        result.extend(&msg.itow.to_le_bytes()[..]);
+        result.extend(&msg.year.to_le_bytes()[..]);
+        result.extend(
+            &[
+                msg.month,
+                msg.day,
+                msg.hour,
+                msg.min,
+                msg.sec,
+                msg.valid.bits(),
+            ][..],
+        );
+        result.extend(&msg.time_acc.to_le_bytes()[..]);
+        result.extend(&msg.nano.to_le_bytes()[..]);
+        result.extend(
+            &[
+                msg.fix_type as u8,
+                msg.flags.bits() | (msg.psm_state & 0x07) << 2,
+                msg.flags2.bits(),
+                msg.num_sv,
+            ][..],
+        );
+        result.extend(&i32_bytes(msg.lon, 1e-7)[..]);
+        result.extend(&i32_bytes(msg.lat, 1e-7)[..]);
+        result.extend(&i32_bytes(msg.height, 1e-3)[..]);
+        result.extend(&i32_bytes(msg.height_msl, 1e-3)[..]);
+        result.extend(&u32_bytes(msg.horizontal_acc, 1e-3)[..]);
+        result.extend(&u32_bytes(msg.vertical_acc, 1e-3)[..]);
+        result.extend(&i32_bytes(msg.vel_north, 1e-3)[..]);
+        result.extend(&i32_bytes(msg.vel_east, 1e-3)[..]);
+        result.extend(&i32_bytes(msg.vel_down, 1e-3)[..]);
+        result.extend(&i32_bytes(msg.ground_speed, 1e-3)[..]);
+        result.extend(&i32_bytes(msg.heading_motion, 1e-5)[..]);
+        result.extend(&u32_bytes(msg.speed_acc, 1e-3)[..]);
+        result.extend(&u32_bytes(msg.heading_acc, 1e-5)[..]);
+        result.extend(&((msg.pdop / 0.01).round() as u16).to_le_bytes()[..]);
+        result.push(msg.flags3.bits() | (msg.last_correction_age & 0x0f) << 1);
+        result.extend(&[0; 5][..]);
+        result.extend(&i32_bytes(msg.heading_vehicle, 1e-5)[..]);
+        result.extend(&((msg.mag_dec / 0.01).round() as i16).to_le_bytes()[..]);
+        result.extend(&((msg.mag_acc / 0.01).round() as u16).to_le_bytes()[..]);
+        result
+    }
+}
+
+impl TryFrom<Vec<u8>> for UbxNavPvt {
+    type Error = UbxError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, UbxError> {
+        let invalid = |offset: usize, field| UbxError::InvalidField {
+            class: Self::CLASS,
+            id: Self::ID,
+            offset,
+            field,
+            value: bytes[offset] as u32,
+        };
+
+        if bytes.len() != 92 {
+            return Err(UbxError::WrongLength {
+                class: Self::CLASS,
+                id: Self::ID,
+                expected: 92,
+                actual: bytes.len(),
+            });
+        }
+
+        let fix_type = UbxNavPvtFixType::try_from(bytes[20]).map_err(|_| invalid(20, "fixType"))?;
+        // reserved bits of the bitfields may be used by newer firmware
+        let valid = UbxNavPvtValid::from_bits_truncate(bytes[11]);
+        let flags = UbxNavPvtFlags::from_bits_truncate(bytes[21]);
+        let flags2 = UbxNavPvtFlags2::from_bits_truncate(bytes[22]);
+        let flags3 = UbxNavPvtFlags3::from_bits_truncate(bytes[78]);
+
+        Ok(UbxNavPvt {
+            itow: u32::from_le_bytes(bytes[0..4].try_into().unwrap()),
+            year: u16::from_le_bytes(bytes[4..6].try_into().unwrap()),
+            month: bytes[6],
+            day: bytes[7],
+            hour: bytes[8],
+            min: bytes[9],
+            sec: bytes[10],
+            valid,
+            time_acc: u32::from_le_bytes(bytes[12..16].try_into().unwrap()),
+            nano: i32::from_le_bytes(bytes[16..20].try_into().unwrap()),
+            fix_type,
+            flags,
+            psm_state: (bytes[21] >> 2) & 0x07,
+            flags2,
+            num_sv: bytes[23],
+            lon: scaled_i32(&bytes[24..28], 1e-7),
+            lat: scaled_i32(&bytes[28..32], 1e-7),
+            height: scaled_i32(&bytes[32..36], 1e-3),
+            height_msl: scaled_i32(&bytes[36..40], 1e-3),
+            horizontal_acc: scaled_u32(&bytes[40..44], 1e-3),
+            vertical_acc: scaled_u32(&bytes[44..48], 1e-3),
+            vel_north: scaled_i32(&bytes[48..52], 1e-3),
+            vel_east: scaled_i32(&bytes[52..56], 1e-3),
+            vel_down: scaled_i32(&bytes[56..60], 1e-3),
+            ground_speed: scaled_i32(&bytes[60..64], 1e-3),
+            heading_motion: scaled_i32(&bytes[64..68], 1e-5),
+            speed_acc: scaled_u32(&bytes[68..72], 1e-3),
+            heading_acc: scaled_u32(&bytes[72..76], 1e-5),
+            pdop: u16::from_le_bytes(bytes[76..78].try_into().unwrap()) as f64 * 0.01,
+            flags3,
+            last_correction_age: (bytes[78] >> 1) & 0x0f,
+            heading_vehicle: scaled_i32(&bytes[84..88], 1e-5),
+            mag_dec: i16::from_le_bytes(bytes[88..90].try_into().unwrap()) as f64 * 0.01,
+            mag_acc: u16::from_le_bytes(bytes[90..92].try_into().unwrap()) as f64 * 0.01,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut bytes = vec![0u8; 92];
+        bytes[4..6].copy_from_slice(&2021u16.to_le_bytes());
+        bytes[11] = 0x07;
+        bytes[20] = 3;
+        bytes[21] = 0x09;
+        bytes[23] = 9;
+        bytes[24..28].copy_from_slice(&210_122_334i32.to_le_bytes());
+        bytes[28..32].copy_from_slice(&522_297_781i32.to_le_bytes());
+        bytes[32..36].copy_from_slice(&(-1234i32).to_le_bytes());
+        bytes[56..60].copy_from_slice(&(-15i32).to_le_bytes());
+        bytes[76..78].copy_from_slice(&153u16.to_le_bytes());
+
+        let pvt = UbxNavPvt::try_from(bytes.clone()).unwrap();
+        assert!(pvt.has_fix());
+        assert!(pvt.utc_valid());
+        assert!((pvt.lat - 52.2297781).abs() < 1e-9);
+        assert!((pvt.lon - 21.0122334).abs() < 1e-9);
+        assert!((pvt.height + 1.234).abs() < 1e-9);
+        assert!((pvt.vel_down + 0.015).abs() < 1e-9);
+        assert_eq!(pvt.psm_state, 2);
+        assert_eq!(pvt.flags, UbxNavPvtFlags::GNSS_FIX_OK);
+        assert_eq!(Vec::<u8>::from(pvt), bytes);
+
+        // reserved bits are ignored
+        bytes[22] = 0x01;
+        bytes[78] = 0x80;
+        let pvt = UbxNavPvt::try_from(bytes.clone()).unwrap();
+        assert!(pvt.flags2.is_empty());
+        assert!(pvt.flags3.is_empty());
+
+        bytes[78] = 0x15;
+        let pvt = UbxNavPvt::try_from(bytes.clone()).unwrap();
+        assert_eq!(pvt.last_correction_age, 10);
+        assert_eq!(pvt.flags3, UbxNavPvtFlags3::INVALID_LLH);
+        assert!(!pvt.has_fix());
+        assert_eq!(Vec::<u8>::from(pvt)[78], 0x15);
+    }
+}
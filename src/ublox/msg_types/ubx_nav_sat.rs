@@ -0,0 +1,256 @@
+use std::convert::{TryFrom, TryInto};
+
+use bitflags::bitflags;
+
+use crate::ublox::{GnssId, UbxError};
+
+bitflags! {
+    pub struct UbxNavSatFlags: u32 {
+        const QUALITY_IND = 0x0000_0007;
+        const SV_USED = 0x0000_0008;
+        const HEALTH = 0x0000_0030;
+        const DIFF_CORR = 0x0000_0040;
+        const SMOOTHED = 0x0000_0080;
+        const ORBIT_SOURCE = 0x0000_0700;
+        const EPH_AVAIL = 0x0000_0800;
+        const ALM_AVAIL = 0x0000_1000;
+        const ANO_AVAIL = 0x0000_2000;
+        const AOP_AVAIL = 0x0000_4000;
+        const SBAS_CORR_USED = 0x0001_0000;
+        const RTCM_CORR_USED = 0x0002_0000;
+        const SLAS_CORR_USED = 0x0004_0000;
+        const SPARTN_CORR_USED = 0x0008_0000;
+        const PR_CORR_USED = 0x0010_0000;
+        const CR_CORR_USED = 0x0020_0000;
+        const DO_CORR_USED = 0x0040_0000;
+        const CLAS_CORR_USED = 0x0080_0000;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UbxNavSatQuality {
+    NoSignal,
+    Searching,
+    Acquired,
+    Unusable,
+    CodeLocked,
+    /// Code and carrier locked and time synchronized.
+    CarrierLocked,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UbxNavSatHealth {
+    Unknown,
+    Healthy,
+    Unhealthy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UbxNavSatOrbitSource {
+    None,
+    Ephemeris,
+    Almanac,
+    AssistNowOffline,
+    AssistNowAutonomous,
+    Other,
+}
+
+impl UbxNavSatFlags {
+    pub fn quality(&self) -> UbxNavSatQuality {
+        match (*self & Self::QUALITY_IND).bits() {
+            0 => UbxNavSatQuality::NoSignal,
+            1 => UbxNavSatQuality::Searching,
+            2 => UbxNavSatQuality::Acquired,
+            3 => UbxNavSatQuality::Unusable,
+            4 => UbxNavSatQuality::CodeLocked,
+            _ => UbxNavSatQuality::CarrierLocked,
+        }
+    }
+
+    pub fn health(&self) -> UbxNavSatHealth {
+        match (*self & Self::HEALTH).bits() >> 4 {
+            1 => UbxNavSatHealth::Healthy,
+            2 => UbxNavSatHealth::Unhealthy,
+            _ => UbxNavSatHealth::Unknown,
+        }
+    }
+
+    pub fn orbit_source(&self) -> UbxNavSatOrbitSource {
+        match (*self & Self::ORBIT_SOURCE).bits() >> 8 {
+            0 => UbxNavSatOrbitSource::None,
+            1 => UbxNavSatOrbitSource::Ephemeris,
+            2 => UbxNavSatOrbitSource::Almanac,
+            3 => UbxNavSatOrbitSource::AssistNowOffline,
+            4 => UbxNavSatOrbitSource::AssistNowAutonomous,
+            _ => UbxNavSatOrbitSource::Other,
+        }
+    }
+}
+
+/// A satellite as seen by the receiver.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UbxNavSatSv {
+    pub gnss_id: GnssId,
+    pub sv_id: u8,
+    /// Carrier to noise ratio, in dBHz.
+    pub cno: u8,
+    /// Elevation in degrees, -90 to 90. Unknown if the satellite isn't tracked.
+    pub elev: i8,
+    /// Azimuth in degrees, 0 to 360. Unknown if the satellite isn't tracked.
+    pub azim: i16,
+    /// Pseudorange residual, in meters.
+    pub pr_res: f64,
+    pub flags: UbxNavSatFlags,
+}
+
+impl From<UbxNavSatSv> for Vec<u8> {
+    fn from(sv: UbxNavSatSv) -> Vec<u8> {
+        let mut result = vec![sv.gnss_id as u8, sv.sv_id, sv.cno, sv.elev as u8];
+        result.extend(&sv.azim.to_le_bytes()[..]);
+        result.extend(&((sv.pr_res / 0.1).round() as i16).to_le_bytes()[..]);
+        result.extend(&sv.flags.bits().to_le_bytes()[..]);
+        result
+    }
+}
+
+/// Offsets in errors are relative to the start of the satellite block.
+impl TryFrom<Vec<u8>> for UbxNavSatSv {
+    type Error = UbxError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, UbxError> {
+        if bytes.len() != 12 {
+            return Err(UbxError::WrongLength {
+                class: UbxNavSat::CLASS,
+                id: UbxNavSat::ID,
+                expected: 12,
+                actual: bytes.len(),
+            });
+        }
+
+        let gnss_id = GnssId::try_from(bytes[0]).map_err(|value| UbxError::UnknownGnssId {
+            class: UbxNavSat::CLASS,
+            id: UbxNavSat::ID,
+            offset: 0,
+            value,
+        })?;
+        // newer firmware sets bits that aren't defined here
+        let flags = UbxNavSatFlags::from_bits_truncate(u32::from_le_bytes(
+            bytes[8..12].try_into().unwrap(),
+        ));
+
+        Ok(UbxNavSatSv {
+            gnss_id,
+            sv_id: bytes[1],
+            cno: bytes[2],
+            elev: bytes[3] as i8,
+            azim: i16::from_le_bytes(bytes[4..6].try_into().unwrap()),
+            pr_res: i16::from_le_bytes(bytes[6..8].try_into().unwrap()) as f64 * 0.1,
+            flags,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UbxNavSat {
+    /// GPS time of week of the navigation epoch, in ms.
+    pub itow: u32,
+    pub version: u8,
+    pub satellites: Vec<UbxNavSatSv>,
+}
+
+impl UbxNavSat {
+    pub const CLASS: u8 = 0x01;
+    pub const ID: u8 = 0x35;
+}
+
+impl From<UbxNavSat> for Vec<u8> {
+    fn from(msg: UbxNavSat) -> Vec<u8> {
+        let mut result = vec![];
+        result.extend(&msg.itow.to_le_bytes()[..]);
+        result.push(msg.version);
+        result.push(msg.satellites.len() as u8);
+        result.extend(&[0, 0][..]);
+        for sv in msg.satellites {
+            result.extend(Vec::<u8>::from(sv));
+        }
+        result
+    }
+}
+
+impl TryFrom<Vec<u8>> for UbxNavSat {
+    type Error = UbxError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, UbxError> {
+        if bytes.len() < 8 {
+            return Err(UbxError::PayloadTooShort {
+                class: Self::CLASS,
+                id: Self::ID,
+                expected: 8,
+                actual: bytes.len(),
+            });
+        }
+
+        let num_svs = bytes[5] as usize;
+        if bytes.len() != 8 + 12 * num_svs {
+            return Err(UbxError::WrongLength {
+                class: Self::CLASS,
+                id: Self::ID,
+                expected: 8 + 12 * num_svs,
+                actual: bytes.len(),
+            });
+        }
+
+        let mut satellites = vec![];
+        for i in 0..num_svs {
+            // satellites of constellations unknown here, like NavIC, are left out rather than
+            // failing the whole message
+            if GnssId::try_from(bytes[8 + 12 * i]).is_err() {
+                continue;
+            }
+            let sv = UbxNavSatSv::try_from(bytes[8 + 12 * i..8 + 12 * (i + 1)].to_vec())
+                .map_err(|err| err.shifted(8 + 12 * i))?;
+            satellites.push(sv);
+        }
+
+        Ok(UbxNavSat {
+            itow: u32::from_le_bytes(bytes[0..4].try_into().unwrap()),
+            version: bytes[4],
+            satellites,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode_satellite() {
+        let mut bytes = vec![0x10, 0x27, 0, 0, 1, 1, 0, 0];
+        bytes.extend(&[0, 12, 43, 61, 0x2c, 0x01, 0xf6, 0xff]);
+        bytes.extend(&0x0000_191fu32.to_le_bytes());
+
+        let msg = UbxNavSat::try_from(bytes.clone()).unwrap();
+        let sv = msg.satellites[0];
+        assert_eq!((sv.gnss_id, sv.sv_id, sv.cno), (GnssId::Gps, 12, 43));
+        assert_eq!((sv.elev, sv.azim), (61, 300));
+        assert!((sv.pr_res + 1.0).abs() < 1e-9);
+        assert_eq!(sv.flags.quality(), UbxNavSatQuality::CarrierLocked);
+        assert_eq!(sv.flags.health(), UbxNavSatHealth::Healthy);
+        assert_eq!(sv.flags.orbit_source(), UbxNavSatOrbitSource::Ephemeris);
+        assert!(sv
+            .flags
+            .contains(UbxNavSatFlags::SV_USED | UbxNavSatFlags::EPH_AVAIL));
+        assert_eq!(Vec::<u8>::from(msg), bytes);
+
+        // a NavIC satellite and an undefined flag bit
+        bytes[5] = 2;
+        bytes[8 + 11] = 0x80;
+        bytes.extend(&[7, 3, 40, 30, 0x5a, 0x00, 0, 0]);
+        bytes.extend(&0x0000_0007u32.to_le_bytes());
+        let msg = UbxNavSat::try_from(bytes).unwrap();
+        assert_eq!(msg.satellites.len(), 1);
+        assert_eq!(msg.satellites[0].sv_id, 12);
+        assert_eq!(msg.satellites[0].flags.bits(), 0x0000_191f);
+    }
+}
@@ -3,6 +3,7 @@ use std::convert::{TryFrom, TryInto};
 use bitflags::bitflags;
 
 use super::GnssId;
+use crate::ublox::UbxError;
 
 bitflags! {
     pub struct UbxRxmRawxRecvStatus: u8 {
@@ -60,52 +61,64 @@ impl From<UbxRxmRawxMeasurement> for Vec<u8> {
     }
 }
 
+/// Offsets in errors are relative to the start of the measurement block.
 impl TryFrom<Vec<u8>> for UbxRxmRawxMeasurement {
-    type Error = String;
+    type Error = UbxError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<UbxRxmRawxMeasurement, UbxError> {
+        let invalid = |offset: usize, field, value: u8| UbxError::InvalidField {
+            class: UbxRxmRawx::CLASS,
+            id: UbxRxmRawx::ID,
+            offset,
+            field,
+            value: value as u32,
+        };
 
-    fn try_from(bytes: Vec<u8>) -> Result<UbxRxmRawxMeasurement, String> {
         if bytes.len() != 32 {
-            return Err(format!(
-                "wrong length for UbxRxmRawxMeasurement: expected 32, got {}",
-                bytes.len()
-            ));
+            return Err(UbxError::WrongLength {
+                class: UbxRxmRawx::CLASS,
+                id: UbxRxmRawx::ID,
+                expected: 32,
+                actual: bytes.len(),
+            });
         }
 
-        let pseudorange =
-            f64::from_le_bytes(bytes[0..8].try_into().map_err(|err| format!("{}", err))?);
-        let carrier_phase =
-            f64::from_le_bytes(bytes[8..16].try_into().map_err(|err| format!("{}", err))?);
-        let doppler =
-            f32::from_le_bytes(bytes[16..20].try_into().map_err(|err| format!("{}", err))?);
-        let gnss_id = GnssId::try_from(bytes[20])?;
+        let pseudorange = f64::from_le_bytes(bytes[0..8].try_into().unwrap());
+        let carrier_phase = f64::from_le_bytes(bytes[8..16].try_into().unwrap());
+        let doppler = f32::from_le_bytes(bytes[16..20].try_into().unwrap());
+        let gnss_id = GnssId::try_from(bytes[20]).map_err(|value| UbxError::UnknownGnssId {
+            class: UbxRxmRawx::CLASS,
+            id: UbxRxmRawx::ID,
+            offset: 20,
+            value,
+        })?;
         let sv_id = bytes[21];
         let freq_id = bytes[23];
-        let locktime =
-            u16::from_le_bytes(bytes[24..26].try_into().map_err(|err| format!("{}", err))?);
+        let locktime = u16::from_le_bytes(bytes[24..26].try_into().unwrap());
         let cno = bytes[26];
 
         let pseudorange_stdev = match bytes[27] {
             x if x < 16 => 2.0f32.powi(x as i32) * 0.01,
             x => {
-                return Err(format!("invalid pseudorange stdev: {}", x));
+                return Err(invalid(27, "prStdev", x));
             }
         };
         let carrier_phase_stdev = match bytes[28] {
             x if x < 15 => Some(x as f32 * 0.004),
             15 => None,
             x => {
-                return Err(format!("invalid carrier phase stdev: {}", x));
+                return Err(invalid(28, "cpStdev", x));
             }
         };
         let doppler_stdev = match bytes[29] {
             x if x < 16 => 2.0f32.powi(x as i32) * 0.002,
             x => {
-                return Err(format!("invalid Doppler stdev: {}", x));
+                return Err(invalid(29, "doStdev", x));
             }
         };
 
-        let trk_status = UbxRxmRawxMeasurementTrkStatus::from_bits(bytes[30])
-            .ok_or_else(|| format!("invalid UbxRxmRawxMeasurementTrkStatus: {}", bytes[30]))?;
+        // newer firmware sets bits that aren't defined here
+        let trk_status = UbxRxmRawxMeasurementTrkStatus::from_bits_truncate(bytes[30]);
 
         Ok(UbxRxmRawxMeasurement {
             pseudorange,
@@ -133,6 +146,11 @@ pub struct UbxRxmRawx {
     pub measurements: Vec<UbxRxmRawxMeasurement>,
 }
 
+impl UbxRxmRawx {
+    pub const CLASS: u8 = 0x02;
+    pub const ID: u8 = 0x15;
+}
+
 impl From<UbxRxmRawx> for Vec<u8> {
     fn from(msg: UbxRxmRawx) -> Vec<u8> {
         let mut result = vec![];
@@ -150,41 +168,43 @@ impl From<UbxRxmRawx> for Vec<u8> {
 }
 
 impl TryFrom<Vec<u8>> for UbxRxmRawx {
-    type Error = String;
+    type Error = UbxError;
 
-    fn try_from(bytes: Vec<u8>) -> Result<Self, String> {
+    fn try_from(bytes: Vec<u8>) -> Result<Self, UbxError> {
         if bytes.len() < 16 {
-            return Err(format!(
-                "UbxRxmRawx too short - expected min. 16 bytes, got {}",
-                bytes.len()
-            ));
-        }
-
-        if (bytes.len() - 16) % 32 != 0 {
-            return Err(format!(
-                "uneven number of bytes for UbxRxmRawx - got {}",
-                bytes.len()
-            ));
+            return Err(UbxError::PayloadTooShort {
+                class: Self::CLASS,
+                id: Self::ID,
+                expected: 16,
+                actual: bytes.len(),
+            });
         }
 
         let length = bytes[11] as usize;
         if bytes.len() != 16 + 32 * length {
-            return Err(format!(
-                "wrong message length for UbxRxmRawx - expected {}, got {}",
-                16 + 32 * length,
-                bytes.len()
-            ));
+            return Err(UbxError::WrongLength {
+                class: Self::CLASS,
+                id: Self::ID,
+                expected: 16 + 32 * length,
+                actual: bytes.len(),
+            });
         }
 
-        let rcv_tow = f64::from_le_bytes(bytes[0..8].try_into().map_err(|err| format!("{}", err))?);
-        let week = u16::from_le_bytes(bytes[8..10].try_into().map_err(|err| format!("{}", err))?);
+        let rcv_tow = f64::from_le_bytes(bytes[0..8].try_into().unwrap());
+        let week = u16::from_le_bytes(bytes[8..10].try_into().unwrap());
         let leap_sec = bytes[10] as i8;
-        let recv_status = UbxRxmRawxRecvStatus::from_bits(bytes[12])
-            .ok_or_else(|| format!("wrong UbxRxmRawxRecvStatus: {}", bytes[12]))?;
+        let recv_status = UbxRxmRawxRecvStatus::from_bits_truncate(bytes[12]);
 
         let mut measurements = vec![];
         for i in 0..length {
-            let measurement = bytes[16 + 32 * i..16 + 32 * (i + 1)].to_vec().try_into()?;
+            // measurements of constellations unknown here, like NavIC, are left out rather than
+            // failing the whole epoch
+            if GnssId::try_from(bytes[16 + 32 * i + 20]).is_err() {
+                continue;
+            }
+            let measurement =
+                UbxRxmRawxMeasurement::try_from(bytes[16 + 32 * i..16 + 32 * (i + 1)].to_vec())
+                    .map_err(|err| err.shifted(16 + 32 * i))?;
             measurements.push(measurement);
         }
 
@@ -197,3 +217,45 @@ impl TryFrom<Vec<u8>> for UbxRxmRawx {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn skips_unknown_gnss() {
+        let measurement = UbxRxmRawxMeasurement {
+            pseudorange: 2.1e7,
+            carrier_phase: 1.1e8,
+            doppler: -1234.5,
+            gnss_id: GnssId::Gps,
+            sv_id: 12,
+            freq_id: 0,
+            locktime: 500,
+            cno: 42,
+            pseudorange_stdev: 0.08,
+            carrier_phase_stdev: Some(0.012),
+            doppler_stdev: 0.02,
+            trk_status: UbxRxmRawxMeasurementTrkStatus::PR_VALID
+                | UbxRxmRawxMeasurementTrkStatus::CP_VALID,
+        };
+        let raw = UbxRxmRawx {
+            rcv_tow: 7300.0,
+            week: 2100,
+            leap_sec: 18,
+            recv_status: UbxRxmRawxRecvStatus::LEAP_SEC,
+            measurements: vec![measurement, measurement],
+        };
+        let mut bytes = Vec::<u8>::from(raw);
+        let clean = UbxRxmRawx::try_from(bytes.clone()).unwrap();
+        assert_eq!(clean.measurements.len(), 2);
+
+        // NavIC, and bits that aren't defined here
+        bytes[16 + 20] = 7;
+        bytes[16 + 32 + 30] |= 0x80;
+        bytes[12] |= 0x10;
+        let decoded = UbxRxmRawx::try_from(bytes).unwrap();
+        assert_eq!(decoded.recv_status, UbxRxmRawxRecvStatus::LEAP_SEC);
+        assert_eq!(decoded.measurements, vec![clean.measurements[1]]);
+    }
+}
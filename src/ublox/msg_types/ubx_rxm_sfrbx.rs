@@ -1,6 +1,7 @@
 use std::convert::TryFrom;
 
 use super::GnssId;
+use crate::ublox::UbxError;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum GpsSubframe {
@@ -38,8 +39,97 @@ pub enum GpsSubframe {
         omega_dot: f64,
         i_dot: f64,
     },
-    Subframe4,
-    Subframe5,
+    Subframe4 {
+        data_id: u8,
+        page: GpsAlmanacPage,
+    },
+    Subframe5 {
+        data_id: u8,
+        page: GpsAlmanacPage,
+    },
+}
+
+/// Reduced orbital parameters of a satellite, broadcast by every satellite in subframes 4 and 5.
+/// Angles are in semicircles, like in the ephemeris.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GpsAlmanac {
+    pub sv_id: u8,
+    pub e: f64,
+    pub toa: u32,
+    /// Inclination relative to 0.3 semicircles.
+    pub delta_i: f64,
+    pub omega_dot: f64,
+    pub sv_health: u8,
+    pub sqrt_a: f64,
+    pub omega0: f64,
+    pub omega_small: f64,
+    pub m0: f64,
+    pub af0: f64,
+    pub af1: f64,
+}
+
+/// Klobuchar ionospheric model coefficients.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GpsIonosphere {
+    pub alpha: [f64; 4],
+    pub beta: [f64; 4],
+}
+
+/// Parameters relating GPS time to UTC, including the leap seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GpsUtc {
+    pub a0: f64,
+    pub a1: f64,
+    pub tot: u32,
+    pub wnt: u8,
+    pub delta_t_ls: i8,
+    pub wn_lsf: u8,
+    pub dn: u8,
+    pub delta_t_lsf: i8,
+}
+
+/// The contents of a page of subframe 4 or 5, identified by the SV ID (page ID) field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GpsAlmanacPage {
+    /// SV IDs 1-32.
+    Almanac(GpsAlmanac),
+    /// Subframe 5, page 25 (SV ID 51): almanac reference time and week, and the health of
+    /// SVs 1-24.
+    Health {
+        toa: u32,
+        week_number: u8,
+        sv_health: [u8; 24],
+    },
+    /// Subframe 4, page 25 (SV ID 63): anti-spoofing and configuration flags of all SVs and the
+    /// health of SVs 25-32.
+    ConfigHealth {
+        sv_config: [u8; 32],
+        sv_health: [u8; 8],
+    },
+    /// Subframe 4, page 18 (SV ID 56).
+    IonoUtc {
+        ionosphere: GpsIonosphere,
+        utc: GpsUtc,
+    },
+    /// Dummy, reserved and other pages that aren't decoded.
+    Other { page_id: u8 },
+}
+
+impl GpsAlmanacPage {
+    pub const HEALTH_PAGE_ID: u8 = 51;
+    pub const IONO_UTC_PAGE_ID: u8 = 56;
+    pub const CONFIG_HEALTH_PAGE_ID: u8 = 63;
+
+    /// The SV ID field of the page - the SV the almanac is for, or a special page ID.
+    pub fn page_id(&self) -> u8 {
+        match *self {
+            GpsAlmanacPage::Almanac(almanac) => almanac.sv_id,
+            GpsAlmanacPage::Health { .. } => Self::HEALTH_PAGE_ID,
+            GpsAlmanacPage::ConfigHealth { .. } => Self::CONFIG_HEALTH_PAGE_ID,
+            GpsAlmanacPage::IonoUtc { .. } => Self::IONO_UTC_PAGE_ID,
+            GpsAlmanacPage::Other { page_id } => page_id,
+        }
+    }
 }
 
 impl GpsSubframe {
@@ -59,37 +149,81 @@ pub struct UbxRxmSfrbxDataGps {
     pub anti_spoof: bool,
     pub alert: bool,
     pub subframe: GpsSubframe,
+    /// Words that failed the parity check, bit 0 being the TLM word. The contents of the
+    /// subframe can't be trusted unless this is 0.
+    pub parity_errors: u16,
+}
+
+impl UbxRxmSfrbxDataGps {
+    pub fn parity_ok(&self) -> bool {
+        self.parity_errors == 0
+    }
+}
+
+/// Parity equations from IS-GPS-200, as masks of the 24 data bits; parities 1, 3 and 6 also
+/// include bit 29 of the previous word (D29*), and 2, 4 and 5 bit 30 (D30*).
+const PARITY_MASKS: [u32; 6] = [0xec7cd2, 0x763e69, 0xbb1f34, 0x5d8f9a, 0xaec7cd, 0x2dea27];
+
+/// The 6 parity bits of a word with the given (not inverted) data bits, following the 30-bit
+/// word `prev`.
+fn parity(data: u32, prev: u32) -> u32 {
+    let d29 = (prev >> 1) & 1;
+    let d30 = prev & 1;
+    let prev_bits = [d29, d30, d29, d30, d30, d29];
+    PARITY_MASKS
+        .iter()
+        .zip(prev_bits)
+        .fold(0, |result, (mask, d)| {
+            result << 1 | (d ^ ((data & mask).count_ones() & 1))
+        })
+}
+
+/// Checks the parity of a 30-bit word and returns its 24 data bits if it's correct.
+///
+/// The transmitted data bits are inverted when D30* is set. Receivers may deliver them either
+/// as transmitted or with the inversion already removed, so both forms are accepted.
+fn check_parity(word: u32, prev: u32) -> Option<u32> {
+    let data = (word >> 6) & 0xffffff;
+    let word_parity = word & 0x3f;
+    if parity(data, prev) == word_parity {
+        Some(data)
+    } else if prev & 1 == 1 && parity(data ^ 0xffffff, prev) == word_parity {
+        Some(data ^ 0xffffff)
+    } else {
+        None
+    }
+}
+
+/// A 30-bit word with the given data bits and its parity.
+fn encode_word(data: u32, prev: u32) -> u32 {
+    data << 6 | parity(data, prev)
 }
 
 impl From<UbxRxmSfrbxDataGps> for Vec<u8> {
     fn from(data: UbxRxmSfrbxDataGps) -> Vec<u8> {
-        let tlm_word: u32 = ((0x8b0000 | data.tlm_message as u32) << 2
-            | if data.integrity_bit { 2 } else { 0 })
-            << 6;
+        let tlm_data: u32 =
+            (0x8b0000 | data.tlm_message as u32) << 2 | if data.integrity_bit { 2 } else { 0 };
 
         let subframe_id: u32 = match data.subframe {
             GpsSubframe::Subframe1 { .. } => 1,
             GpsSubframe::Subframe2 { .. } => 2,
             GpsSubframe::Subframe3 { .. } => 3,
-            GpsSubframe::Subframe4 => 4,
-            GpsSubframe::Subframe5 => 5,
+            GpsSubframe::Subframe4 { .. } => 4,
+            GpsSubframe::Subframe5 { .. } => 5,
         };
 
-        let how_word: u32 = (data.tow << 7
+        let how_data: u32 = data.tow << 7
             | subframe_id << 2
             | if data.anti_spoof { 32 } else { 0 }
-            | if data.alert { 64 } else { 0 })
-            << 6;
+            | if data.alert { 64 } else { 0 };
 
         let mut result = vec![];
-
-        result.extend(&tlm_word.to_le_bytes()[..]);
-        result.extend(&how_word.to_le_bytes()[..]);
-
-        for _ in 0..32 {
-            result.push(0);
+        let mut prev = 0;
+        for data in [tlm_data, how_data].iter().chain(&[0; 8]) {
+            let word = encode_word(*data, prev);
+            result.extend(&word.to_le_bytes()[..]);
+            prev = word;
         }
-
         result
     }
 }
@@ -186,31 +320,146 @@ fn decode_subframe3(words: &[u32]) -> GpsSubframe {
     }
 }
 
+fn decode_almanac(sv_id: u8, words: &[u32]) -> GpsAlmanac {
+    let e = to_f64_unsigned(words[0] & 65535, -21);
+    let toa = (words[1] >> 16) << 12;
+    let delta_i = to_f64_signed(words[1] & 65535, 16, -19);
+    let omega_dot = to_f64_signed(words[2] >> 8, 16, -38);
+    let sv_health = (words[2] & 255) as u8;
+    let sqrt_a = to_f64_unsigned(words[3], -11);
+    let omega0 = to_f64_signed(words[4], 24, -23);
+    let omega_small = to_f64_signed(words[5], 24, -23);
+    let m0 = to_f64_signed(words[6], 24, -23);
+    let af0 = to_f64_signed(((words[7] >> 16) << 3) | ((words[7] >> 2) & 7), 11, -20);
+    let af1 = to_f64_signed((words[7] >> 5) & 2047, 11, -38);
+
+    GpsAlmanac {
+        sv_id,
+        e,
+        toa,
+        delta_i,
+        omega_dot,
+        sv_health,
+        sqrt_a,
+        omega0,
+        omega_small,
+        m0,
+        af0,
+        af1,
+    }
+}
+
+/// Splits the data words into consecutive fields of `bits` bits, starting at bit `skip` of the
+/// first word.
+fn unpack_fields(words: &[u32], skip: usize, bits: usize, result: &mut [u8]) {
+    for (i, field) in result.iter_mut().enumerate() {
+        let start = skip + i * bits;
+        let mut value = 0;
+        for bit in start..start + bits {
+            value = value << 1 | (words[bit / 24] >> (23 - bit % 24)) & 1;
+        }
+        *field = value as u8;
+    }
+}
+
+fn decode_iono_utc(words: &[u32]) -> GpsAlmanacPage {
+    let byte = |word: usize, shift: u32| (words[word] >> shift) & 255;
+    let ionosphere = GpsIonosphere {
+        alpha: [
+            to_f64_signed(byte(0, 8), 8, -30),
+            to_f64_signed(byte(0, 0), 8, -27),
+            to_f64_signed(byte(1, 16), 8, -24),
+            to_f64_signed(byte(1, 8), 8, -24),
+        ],
+        beta: [
+            to_f64_signed(byte(1, 0), 8, 11),
+            to_f64_signed(byte(2, 16), 8, 14),
+            to_f64_signed(byte(2, 8), 8, 16),
+            to_f64_signed(byte(2, 0), 8, 16),
+        ],
+    };
+    let utc = GpsUtc {
+        a1: to_f64_signed(words[3], 24, -50),
+        a0: to_f64_signed((words[4] << 8) | byte(5, 16), 32, -30),
+        tot: byte(5, 8) << 12,
+        wnt: byte(5, 0) as u8,
+        delta_t_ls: byte(6, 16) as i8,
+        wn_lsf: byte(6, 8) as u8,
+        dn: byte(6, 0) as u8,
+        delta_t_lsf: byte(7, 16) as i8,
+    };
+    GpsAlmanacPage::IonoUtc { ionosphere, utc }
+}
+
+/// Decodes a page of subframe 4 or 5; `words` are the data words 3-10.
+fn decode_page(subframe_id: u32, words: &[u32]) -> (u8, GpsAlmanacPage) {
+    let data_id = (words[0] >> 22) as u8;
+    let page_id = ((words[0] >> 16) & 63) as u8;
+    let page = match (subframe_id, page_id) {
+        (_, 1..=32) => GpsAlmanacPage::Almanac(decode_almanac(page_id, words)),
+        (5, GpsAlmanacPage::HEALTH_PAGE_ID) => {
+            let mut sv_health = [0; 24];
+            unpack_fields(&words[1..], 0, 6, &mut sv_health);
+            GpsAlmanacPage::Health {
+                toa: ((words[0] >> 8) & 255) << 12,
+                week_number: (words[0] & 255) as u8,
+                sv_health,
+            }
+        }
+        (4, GpsAlmanacPage::CONFIG_HEALTH_PAGE_ID) => {
+            let mut sv_config = [0; 32];
+            unpack_fields(words, 8, 4, &mut sv_config);
+            let mut sv_health = [0; 8];
+            unpack_fields(&words[5..], 18, 6, &mut sv_health);
+            GpsAlmanacPage::ConfigHealth {
+                sv_config,
+                sv_health,
+            }
+        }
+        (4, GpsAlmanacPage::IONO_UTC_PAGE_ID) => decode_iono_utc(words),
+        _ => GpsAlmanacPage::Other { page_id },
+    };
+    (data_id, page)
+}
+
+/// Offsets in errors are relative to the start of the subframe data.
 impl TryFrom<Vec<u8>> for UbxRxmSfrbxDataGps {
-    type Error = String;
+    type Error = UbxError;
 
-    fn try_from(bytes: Vec<u8>) -> Result<Self, String> {
+    fn try_from(bytes: Vec<u8>) -> Result<Self, UbxError> {
         if bytes.len() != 40 {
-            return Err(format!(
-                "UbxRxmSfrbxDataGps: expected 40 bytes, got {}",
-                bytes.len()
-            ));
+            return Err(UbxError::WrongLength {
+                class: UbxRxmSfrbx::CLASS,
+                id: UbxRxmSfrbx::ID,
+                expected: 40,
+                actual: bytes.len(),
+            });
         }
 
         let mut words = vec![];
-        for word in bytes.chunks(4) {
-            let word_32 =
-                u32::from_le_bytes(<[u8; 4]>::try_from(word).map_err(|err| format!("{}", err))?);
-            // TODO: validate parity
-            words.push((word_32 >> 6) & 0x00FFFFFF);
+        let mut parity_errors = 0;
+        // the last word of a subframe always ends with two zero bits
+        let mut prev = 0;
+        for (i, word) in bytes.chunks(4).enumerate() {
+            let word_32 = u32::from_le_bytes(<[u8; 4]>::try_from(word).unwrap()) & 0x3fffffff;
+            match check_parity(word_32, prev) {
+                Some(data) => words.push(data),
+                None => {
+                    parity_errors |= 1 << i;
+                    words.push((word_32 >> 6) & 0x00FFFFFF);
+                }
+            }
+            prev = word_32;
         }
 
         if words[0] >> 16 != 0x8b {
-            return Err(format!(
-                "UbxRxmSfrbxDataGps: wrong preamble, expected {}, got {}",
-                0x8b,
-                words[0] >> 16
-            ));
+            return Err(UbxError::InvalidField {
+                class: UbxRxmSfrbx::CLASS,
+                id: UbxRxmSfrbx::ID,
+                offset: 0,
+                field: "preamble",
+                value: words[0] >> 16,
+            });
         }
 
         let tlm_message = ((words[0] >> 2) & 0x3FFF) as u16;
@@ -221,10 +470,22 @@ impl TryFrom<Vec<u8>> for UbxRxmSfrbxDataGps {
             1 => decode_subframe1(&words[2..]),
             2 => decode_subframe2(&words[2..]),
             3 => decode_subframe3(&words[2..]),
-            4 => GpsSubframe::Subframe4,
-            5 => GpsSubframe::Subframe5,
+            4 => {
+                let (data_id, page) = decode_page(4, &words[2..]);
+                GpsSubframe::Subframe4 { data_id, page }
+            }
+            5 => {
+                let (data_id, page) = decode_page(5, &words[2..]);
+                GpsSubframe::Subframe5 { data_id, page }
+            }
             x => {
-                return Err(format!("UbxRxmSfrbxDataGps: invalid subframe ID: {}", x));
+                return Err(UbxError::InvalidField {
+                    class: UbxRxmSfrbx::CLASS,
+                    id: UbxRxmSfrbx::ID,
+                    offset: 4,
+                    field: "subframe ID",
+                    value: x,
+                });
             }
         };
 
@@ -238,6 +499,7 @@ impl TryFrom<Vec<u8>> for UbxRxmSfrbxDataGps {
             anti_spoof,
             alert,
             subframe,
+            parity_errors,
         })
     }
 }
@@ -275,50 +537,66 @@ pub struct UbxRxmSfrbx {
     pub data: UbxRxmSfrbxData,
 }
 
+impl UbxRxmSfrbx {
+    pub const CLASS: u8 = 0x02;
+    pub const ID: u8 = 0x13;
+}
+
 impl From<UbxRxmSfrbx> for Vec<u8> {
     fn from(msg: UbxRxmSfrbx) -> Vec<u8> {
-        let mut result = vec![];
-        result.push(msg.gnss_id as u8);
-        result.push(msg.sv_id);
-        result.push(0);
-        result.push(msg.freq_id);
-        result.push(msg.data.words());
-        result.push(0);
-        result.push(msg.version);
-        result.push(0);
+        let mut result = vec![
+            msg.gnss_id as u8,
+            msg.sv_id,
+            0,
+            msg.freq_id,
+            msg.data.words(),
+            0,
+            msg.version,
+            0,
+        ];
         result.extend(Vec::<u8>::from(msg.data));
         result
     }
 }
 
 impl TryFrom<Vec<u8>> for UbxRxmSfrbx {
-    type Error = String;
+    type Error = UbxError;
 
-    fn try_from(bytes: Vec<u8>) -> Result<Self, String> {
+    fn try_from(bytes: Vec<u8>) -> Result<Self, UbxError> {
         if bytes.len() < 8 {
-            return Err(format!(
-                "UbxRxmSfrbx: data too short; expected min 8 bytes, got {}",
-                bytes.len()
-            ));
+            return Err(UbxError::PayloadTooShort {
+                class: Self::CLASS,
+                id: Self::ID,
+                expected: 8,
+                actual: bytes.len(),
+            });
         }
 
-        let gnss_id = GnssId::try_from(bytes[0])?;
+        let gnss_id = GnssId::try_from(bytes[0]).map_err(|value| UbxError::UnknownGnssId {
+            class: Self::CLASS,
+            id: Self::ID,
+            offset: 0,
+            value,
+        })?;
         let sv_id = bytes[1];
         let freq_id = bytes[3];
         let length = bytes[4] as usize;
 
         if bytes.len() != 8 + 4 * length {
-            return Err(format!(
-                "UbxRxmSfrbx: wrong length, expected {}, got {}",
-                8 + 4 * length,
-                bytes.len()
-            ));
+            return Err(UbxError::WrongLength {
+                class: Self::CLASS,
+                id: Self::ID,
+                expected: 8 + 4 * length,
+                actual: bytes.len(),
+            });
         }
 
         let version = bytes[6];
 
         let data = match gnss_id {
-            GnssId::Gps => UbxRxmSfrbxData::Gps(UbxRxmSfrbxDataGps::try_from(bytes[8..].to_vec())?),
+            GnssId::Gps => UbxRxmSfrbxData::Gps(
+                UbxRxmSfrbxDataGps::try_from(bytes[8..].to_vec()).map_err(|err| err.shifted(8))?,
+            ),
             _ => UbxRxmSfrbxData::Other(bytes[8..].to_vec()),
         };
 
@@ -331,3 +609,113 @@ impl TryFrom<Vec<u8>> for UbxRxmSfrbx {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A subframe with the given data words 3-10.
+    fn subframe_bytes(subframe_id: u32, data_words: [u32; 8]) -> Vec<u8> {
+        let mut words = vec![0x8b0000, 1000 << 7 | subframe_id << 2];
+        words.extend_from_slice(&data_words);
+        let mut prev = 0;
+        let mut result = vec![];
+        for data in words {
+            prev = encode_word(data, prev);
+            result.extend_from_slice(&prev.to_le_bytes());
+        }
+        result
+    }
+
+    #[test]
+    fn almanac_pages() {
+        let bytes = subframe_bytes(
+            5,
+            [
+                1 << 22 | 5 << 16 | 0x1234,
+                0x90 << 16 | 0xfff0,
+                0xfd00 << 8,
+                0xa10d00,
+                0xc00000,
+                0x200000,
+                0x000001,
+                0xff << 16 | 1 << 5 | 7 << 2,
+            ],
+        );
+        let data = UbxRxmSfrbxDataGps::try_from(bytes).unwrap();
+        let expected = GpsAlmanac {
+            sv_id: 5,
+            e: 0x1234 as f64 * 2.0_f64.powi(-21),
+            toa: 0x90 << 12,
+            delta_i: -16.0 * 2.0_f64.powi(-19),
+            omega_dot: -768.0 * 2.0_f64.powi(-38),
+            sv_health: 0,
+            sqrt_a: 0xa10d00 as f64 * 2.0_f64.powi(-11),
+            omega0: -0.5,
+            omega_small: 0.25,
+            m0: 2.0_f64.powi(-23),
+            af0: -(2.0_f64.powi(-20)),
+            af1: 2.0_f64.powi(-38),
+        };
+        assert_eq!(
+            data.subframe,
+            GpsSubframe::Subframe5 {
+                data_id: 1,
+                page: GpsAlmanacPage::Almanac(expected)
+            }
+        );
+
+        let bytes = subframe_bytes(
+            5,
+            [
+                1 << 22 | 51 << 16 | 0x90 << 8 | 21,
+                0x3f << 18,
+                0,
+                0,
+                0,
+                0,
+                0x21,
+                0,
+            ],
+        );
+        let mut sv_health = [0; 24];
+        sv_health[0] = 0x3f;
+        sv_health[23] = 0x21;
+        assert_eq!(
+            UbxRxmSfrbxDataGps::try_from(bytes).unwrap().subframe,
+            GpsSubframe::Subframe5 {
+                data_id: 1,
+                page: GpsAlmanacPage::Health {
+                    toa: 0x90 << 12,
+                    week_number: 21,
+                    sv_health
+                }
+            }
+        );
+    }
+
+    #[test]
+    fn parity() {
+        let bytes = subframe_bytes(4, [0x123456, 0xabcdef, 0x00ff00, 0, 0, 0, 0, 0]);
+        let data = UbxRxmSfrbxDataGps::try_from(bytes.clone()).unwrap();
+        assert!(data.parity_ok());
+
+        // words transmitted with the data bits inverted after D30* = 1
+        let mut inverted = bytes.clone();
+        for i in 1..10 {
+            let prev = u32::from_le_bytes(<[u8; 4]>::try_from(&bytes[4 * i - 4..4 * i]).unwrap());
+            if prev & 1 == 1 {
+                let word =
+                    u32::from_le_bytes(<[u8; 4]>::try_from(&bytes[4 * i..4 * i + 4]).unwrap());
+                inverted[4 * i..4 * i + 4].copy_from_slice(&(word ^ 0x3fffffc0).to_le_bytes());
+            }
+        }
+        assert_ne!(inverted, bytes);
+        assert_eq!(UbxRxmSfrbxDataGps::try_from(inverted).unwrap(), data);
+
+        let mut corrupted = bytes;
+        corrupted[4 * 5 + 2] ^= 0x10;
+        let data = UbxRxmSfrbxDataGps::try_from(corrupted).unwrap();
+        assert_eq!(data.parity_errors, 1 << 5);
+    }
+}
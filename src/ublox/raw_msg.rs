@@ -1,5 +1,22 @@
 use std::convert::TryFrom;
 
+use super::UbxError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumMode {
+    /// Frames with a checksum mismatch are rejected.
+    Strict,
+    /// Frames with a checksum mismatch are decoded anyway, keeping the checksum from the wire.
+    /// Meant for forensic decoding of damaged captures.
+    Lenient,
+}
+
+impl Default for ChecksumMode {
+    fn default() -> Self {
+        ChecksumMode::Strict
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct UbloxRawMsg {
     class: u8,
@@ -35,6 +52,57 @@ impl UbloxRawMsg {
         self.checksum
     }
 
+    /// Returns false for messages decoded in lenient mode whose checksum didn't match.
+    pub fn has_valid_checksum(&self) -> bool {
+        self.checksum == Self::calc_checksum(self.class, self.id, &self.payload)
+    }
+
+    pub fn from_bytes(bytes: &[u8], mode: ChecksumMode) -> Result<Self, UbxError> {
+        if bytes.len() < 8 {
+            return Err(UbxError::FrameTooShort {
+                expected: 8,
+                actual: bytes.len(),
+            });
+        }
+
+        if bytes[0..2] != [0xb5, 0x62] {
+            return Err(UbxError::WrongHeader {
+                found: [bytes[0], bytes[1]],
+            });
+        }
+
+        let class = bytes[2];
+        let id = bytes[3];
+        let length = u16::from_le_bytes([bytes[4], bytes[5]]) as usize;
+
+        if bytes.len() < 8 + length {
+            return Err(UbxError::FrameTooShort {
+                expected: 8 + length,
+                actual: bytes.len(),
+            });
+        }
+
+        let payload = bytes[6..6 + length].to_vec();
+        let checksum = [bytes[6 + length], bytes[7 + length]];
+        let expected = Self::calc_checksum(class, id, &payload);
+
+        if checksum != expected && mode == ChecksumMode::Strict {
+            return Err(UbxError::BadChecksum {
+                class,
+                id,
+                expected,
+                actual: checksum,
+            });
+        }
+
+        Ok(UbloxRawMsg {
+            class,
+            id,
+            payload,
+            checksum,
+        })
+    }
+
     fn calc_checksum(class: u8, id: u8, payload: &[u8]) -> [u8; 2] {
         let mut ck_a = 0u8;
         let mut ck_b = 0u8;
@@ -59,38 +127,10 @@ impl UbloxRawMsg {
 }
 
 impl TryFrom<Vec<u8>> for UbloxRawMsg {
-    type Error = String;
-
-    fn try_from(bytes: Vec<u8>) -> Result<Self, String> {
-        if &bytes[0..2] != &[0xb5, 0x62] {
-            return Err(format!(
-                "wrong header: expected [181, 98], got {:?}",
-                &bytes[0..2]
-            ));
-        }
-
-        if bytes.len() < 8 {
-            return Err(format!(
-                "message too short: expected at least 8 bytes, got {}",
-                bytes.len()
-            ));
-        }
-
-        let class = bytes[2];
-        let id = bytes[3];
-        let length = u16::from_le_bytes([bytes[4], bytes[5]]) as usize;
-
-        if bytes.len() < 8 + length {
-            return Err(format!(
-                "message too short: expected {} bytes, got {}",
-                8 + length,
-                bytes.len()
-            ));
-        }
-
-        let payload = bytes[6..6 + length].to_vec();
+    type Error = UbxError;
 
-        Ok(Self::new(class, id, payload))
+    fn try_from(bytes: Vec<u8>) -> Result<Self, UbxError> {
+        Self::from_bytes(&bytes, ChecksumMode::Strict)
     }
 }
 
@@ -108,7 +148,7 @@ impl From<UbloxRawMsg> for Vec<u8> {
 mod test {
     use std::convert::TryInto;
 
-    use super::UbloxRawMsg;
+    use super::{ChecksumMode, UbloxRawMsg, UbxError};
 
     #[test]
     fn simple_test_from_vec() {
@@ -127,6 +167,27 @@ mod test {
         );
     }
 
+    #[test]
+    fn bad_checksum() {
+        let bytes = vec![
+            0xb5, 0x62, 0x01, 0x02, 0x04, 0x00, b't', b'e', b'x', b't', 0xc7, 0x87,
+        ];
+        let msg: Result<UbloxRawMsg, _> = bytes.clone().try_into();
+        assert_eq!(
+            msg,
+            Err(UbxError::BadChecksum {
+                class: 0x01,
+                id: 0x02,
+                expected: [0xcc, 0x91],
+                actual: [0xc7, 0x87],
+            })
+        );
+
+        let msg = UbloxRawMsg::from_bytes(&bytes, ChecksumMode::Lenient).unwrap();
+        assert!(!msg.has_valid_checksum());
+        assert_eq!(Vec::<u8>::from(msg), bytes);
+    }
+
     #[test]
     fn simple_test_to_vec() {
         let payload = vec![b't', b'e', b's', b't'];
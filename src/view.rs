@@ -0,0 +1,156 @@
+use std::{
+    io::{Read, Seek, Write},
+    sync::{Arc, RwLock},
+    thread,
+    time::Instant,
+};
+
+use glium::{
+    glutin::{
+        event::{ElementState, Event, VirtualKeyCode, WindowEvent},
+        event_loop::{ControlFlow, EventLoop},
+        window::WindowBuilder,
+        ContextBuilder,
+    },
+    Display,
+};
+use gps_util::{
+    config::ReceiverConfig,
+    gps_status::GpsStatus,
+    port_buffer::{Message, PortBuffer},
+    replay::{Replay, ReplayEvent},
+    time_source::{ReplayClock, TimeSource},
+};
+use nalgebra::Vector3;
+
+use crate::{configure_receiver, renderer::Renderer};
+
+fn port_thread<P: Read + Write>(
+    mut port: PortBuffer<P>,
+    config: Option<ReceiverConfig>,
+    gps_status: Arc<RwLock<GpsStatus>>,
+) {
+    if let Some(ref config) = config {
+        if let Err(err) = configure_receiver(&mut port, config) {
+            println!("Couldn't configure the receiver: {}\n", err);
+        }
+    }
+
+    loop {
+        if let Err(err) = port.read() {
+            println!("Error! {}\n", err);
+            continue;
+        }
+        while let Some(msg) = port.read_msg() {
+            let msg = match msg {
+                Ok(msg) => msg,
+                Err(err) => {
+                    println!("Skipped invalid data: {}\n", err);
+                    continue;
+                }
+            };
+            println!("{:#?}", msg);
+            if let Message::Ublox(msg) = msg {
+                gps_status.write().unwrap().consume_msg(&msg);
+            }
+        }
+    }
+}
+
+fn replay_thread<R: Read + Seek>(mut replay: Replay<R>, gps_status: Arc<RwLock<GpsStatus>>) {
+    loop {
+        match replay.next_event() {
+            Ok(Some(ReplayEvent::Message(Message::Ublox(msg)))) => {
+                gps_status.write().unwrap().consume_msg(&msg)
+            }
+            Ok(Some(ReplayEvent::Message(Message::Nmea(_)))) => {}
+            Ok(Some(ReplayEvent::Error(err))) => println!("Skipped invalid data: {}\n", err),
+            Ok(Some(ReplayEvent::Rewound)) => gps_status.write().unwrap().clear(),
+            Ok(None) => {
+                println!("End of the log\n{:#?}", replay.stats());
+                return;
+            }
+            Err(err) => {
+                println!("Error! {}\n", err);
+                return;
+            }
+        }
+    }
+}
+
+/// The renderer uses a coordinate system where Y is along the Earth's axis.
+fn render_frame(ecef: Vector3<f64>) -> Vector3<f64> {
+    Vector3::new(ecef.x, ecef.z, -ecef.y)
+}
+
+pub fn run<P: Read + Write + Send + 'static>(
+    port: PortBuffer<P>,
+    config: Option<ReceiverConfig>,
+) -> ! {
+    let gps_status = Arc::new(RwLock::new(GpsStatus::new()));
+    let gps_status_clone = gps_status.clone();
+    let _port_thread = thread::spawn(move || port_thread(port, config, gps_status_clone));
+    show(gps_status, None)
+}
+
+/// Shows a replayed log. Space pauses, the left and right arrows seek by a minute and the up and
+/// down arrows change the speed.
+pub fn replay<R: Read + Seek + Send + 'static>(replay: Replay<R>) -> ! {
+    let clock = replay.clock();
+    let gps_status = Arc::new(RwLock::new(GpsStatus::with_time_source(clock.clone())));
+    let gps_status_clone = gps_status.clone();
+    let _replay_thread = thread::spawn(move || replay_thread(replay, gps_status_clone));
+    show(gps_status, Some(clock))
+}
+
+fn show(gps_status: Arc<RwLock<GpsStatus>>, replay_clock: Option<Arc<ReplayClock>>) -> ! {
+    let event_loop = EventLoop::new();
+
+    let wb = WindowBuilder::new().with_title("GPS Visualization");
+    let cb = ContextBuilder::new();
+    let display = Display::new(wb, cb, &event_loop).unwrap();
+
+    let mut renderer = Renderer::new(&display);
+
+    let start = Instant::now();
+
+    // event handling loop (in main thread)
+    event_loop.run(move |ev, _, control_flow| {
+        match ev {
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::CloseRequested => {
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+                WindowEvent::KeyboardInput { input, .. } => {
+                    if let (ElementState::Pressed, Some(key), Some(clock)) =
+                        (input.state, input.virtual_keycode, &replay_clock)
+                    {
+                        match key {
+                            VirtualKeyCode::Space => clock.toggle_pause(),
+                            VirtualKeyCode::Left => clock.seek(clock.now() - 60.0),
+                            VirtualKeyCode::Right => clock.seek(clock.now() + 60.0),
+                            VirtualKeyCode::Up => clock.set_speed(clock.speed() * 2.0),
+                            VirtualKeyCode::Down => clock.set_speed(clock.speed() / 2.0),
+                            _ => (),
+                        }
+                    }
+                }
+                _ => return,
+            },
+            Event::MainEventsCleared => {
+                let t = start.elapsed().as_secs_f32();
+                let gps_t = gps_status.read().unwrap().gps_time();
+                let satellites: Vec<_> = gps_status
+                    .read()
+                    .unwrap()
+                    .complete_satellites()
+                    .map(|(sv_id, orb_elem)| (sv_id, render_frame(orb_elem.position(gps_t))))
+                    .collect();
+                renderer.draw(&display, t, satellites);
+            }
+            _ => (),
+        }
+        *control_flow = ControlFlow::Poll;
+    });
+}